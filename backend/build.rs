@@ -10,7 +10,7 @@ fn main() {
     let out_dir = Path::new(&out_dir_env);
     protoc_rust::Codegen::new()
         .out_dir(out_dir)
-        .inputs(&["protos/messages.proto"])
+        .inputs(["protos/messages.proto"])
         .include("protos")
         .run()
         .expect("Running protoc failed.");
@@ -21,7 +21,7 @@ fn main() {
     for line in code.lines() {
         if !line.starts_with("//!") && !line.starts_with("#!") {
             writer.write_all(line.as_bytes()).unwrap();
-            writer.write_all(&[b'\n']).unwrap();
+            writer.write_all(b"\n").unwrap();
         }
     }
 }
\ No newline at end of file
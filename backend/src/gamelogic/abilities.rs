@@ -0,0 +1,97 @@
+use super::ControllerPoint;
+use crate::{AbilityType, SmokeCloudResponse};
+
+pub(super) const DASH_MOTOR: f32 = 4.0;
+const SMOKE_RADIUS: f32 = 80.0;
+const SMOKE_DURATION: i32 = 300;
+
+/// The one active ability a tank brings into the match, used with `PlayerInput::UseAbility`.
+#[derive(Debug)]
+pub(super) struct Ability {
+    pub(super) ability_type: AbilityType,
+    /// Ticks left of the ability being in effect.
+    active: i32,
+    /// Ticks until it can be used again, counted from when it was used.
+    pub(super) cooldown: i32
+}
+
+impl Ability {
+    pub(super) fn new(ability_type: AbilityType) -> Self {
+        Ability {
+            ability_type,
+            active: 0,
+            cooldown: 0
+        }
+    }
+    fn duration(&self) -> i32 {
+        match self.ability_type {
+            AbilityType::dash => 8,
+            AbilityType::energy_shield => 90,
+            _ => 1
+        }
+    }
+    fn cooldown_time(&self) -> i32 {
+        match self.ability_type {
+            AbilityType::dash => 180,
+            AbilityType::energy_shield => 600,
+            _ => 480
+        }
+    }
+    /// Returns true if the ability was ready and is now in effect.
+    pub(super) fn activate(&mut self) -> bool {
+        if self.cooldown > 0 {
+            return false;
+        }
+        self.active = self.duration();
+        self.cooldown = self.cooldown_time();
+        true
+    }
+    pub(super) fn tick(&mut self) {
+        if self.active > 0 {
+            self.active -= 1;
+        }
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+        }
+    }
+    /// Ends whatever is in effect, the cooldown keeps running.
+    pub(super) fn cancel(&mut self) {
+        self.active = 0;
+    }
+    pub(super) fn is_active(&self) -> bool {
+        self.active > 0
+    }
+    pub(super) fn is_using(&self, ability_type: AbilityType) -> bool {
+        self.ability_type == ability_type && self.is_active()
+    }
+    pub(super) fn is_busy(&self) -> bool {
+        self.active > 0 || self.cooldown > 0
+    }
+}
+
+/// Smoke dropped by a tank. Homing rockets lose track of tanks inside it.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SmokeCloud {
+    pub(super) position: ControllerPoint,
+    /// Ticks left.
+    pub(super) remaining: i32
+}
+
+impl SmokeCloud {
+    pub(super) fn new(position: ControllerPoint) -> Self {
+        SmokeCloud {
+            position,
+            remaining: SMOKE_DURATION
+        }
+    }
+    pub(super) fn covers(&self, point: ControllerPoint) -> bool {
+        (point.x - self.position.x).powi(2) + (point.y - self.position.y).powi(2) <= SMOKE_RADIUS.powi(2)
+    }
+    pub(super) fn to_buffer_smoke_cloud(self, id: i32) -> SmokeCloudResponse {
+        let mut smoke_cloud_response = SmokeCloudResponse::new();
+        smoke_cloud_response.set_id(id);
+        smoke_cloud_response.set_position(self.position.to_buffer_point());
+        smoke_cloud_response.set_radius(SMOKE_RADIUS as i32);
+        smoke_cloud_response
+    }
+}
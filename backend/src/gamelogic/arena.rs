@@ -0,0 +1,444 @@
+use super::ControllerPoint;
+use crate::{BaseResponse, ObstacleMaterial, ObstacleResponse, ObstacleShape, SpawnPointResponse, SurfaceType, Team, TerrainZoneResponse};
+use protobuf::RepeatedField;
+
+const SWEEP_EPSILON: f32 = 0.0001;
+const FREE_POINT_ATTEMPTS: i32 = 100;
+/// Smallest arena a map or the generator can make, room for a few tanks to get out of each other's way.
+pub(super) const MIN_ARENA_SIZE: i32 = 400;
+
+/// Unit direction as `(x, y)`.
+pub(super) type Normal = (f32, f32);
+
+/// A piece of cover. Polygons have to be convex, rectangles are kept apart so
+/// clients can draw them without triangulating.
+#[derive(Debug, Clone)]
+pub(super) struct Obstacle {
+    pub(super) id: i32,
+    pub(super) shape: ObstacleShape,
+    /// Corners in order, either winding.
+    pub(super) vertices: Vec<ControllerPoint>,
+    /// How tall the obstacle is, shells arcing higher than this fly over it.
+    pub(super) height: f32,
+    pub(super) material: ObstacleMaterial,
+    /// What is left of it, `None` for obstacles explosions can't break.
+    pub(super) hit_points: Option<i32>,
+    pub(super) max_hit_points: i32
+}
+
+/// Where a swept shape first touched an obstacle.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SweepHit {
+    /// Fraction of the sweep travelled before the contact.
+    pub(super) t: f32,
+    pub(super) position: ControllerPoint,
+    /// Outward normal of the side that was hit, `None` when it came down on top.
+    pub(super) normal: Option<Normal>
+}
+
+impl Obstacle {
+    pub(super) fn rectangle(id: i32, x: f32, y: f32, width: f32, depth: f32, height: f32) -> Self {
+        Obstacle {
+            id,
+            shape: ObstacleShape::rectangle,
+            vertices: vec![
+                ControllerPoint { x, y },
+                ControllerPoint { x: x + width, y },
+                ControllerPoint { x: x + width, y: y + depth },
+                ControllerPoint { x, y: y + depth }
+            ],
+            height,
+            material: ObstacleMaterial::concrete,
+            hit_points: None,
+            max_hit_points: 0
+        }
+    }
+    pub(super) fn polygon(id: i32, vertices: Vec<ControllerPoint>, height: f32) -> Self {
+        Obstacle {
+            id,
+            shape: ObstacleShape::polygon,
+            vertices,
+            height,
+            material: ObstacleMaterial::concrete,
+            hit_points: None,
+            max_hit_points: 0
+        }
+    }
+    /// Makes the obstacle out of `material`, with the material's usual hit points unless given.
+    pub(super) fn with_material(mut self, material: ObstacleMaterial, hit_points: Option<i32>) -> Self {
+        let default_hit_points = match material {
+            ObstacleMaterial::wooden_crate => Some(60),
+            ObstacleMaterial::brick_wall => Some(200),
+            ObstacleMaterial::explosive_barrel => Some(30),
+            _ => None
+        };
+        self.material = material;
+        self.hit_points = hit_points.or(default_hit_points);
+        self.max_hit_points = self.hit_points.unwrap_or(0);
+        self
+    }
+    /// False for indestructible obstacles and ones already destroyed this tick.
+    pub(super) fn is_destructible(&self) -> bool {
+        self.hit_points.is_some_and(|hit_points| hit_points > 0)
+    }
+    /// Returns true if the damage destroyed it. Indestructible obstacles ignore damage.
+    pub(super) fn take_damage(&mut self, damage: f32) -> bool {
+        let Some(hit_points) = self.hit_points.as_mut() else {
+            return false;
+        };
+        *hit_points -= damage.round() as i32;
+        *hit_points <= 0
+    }
+    pub(super) fn center(&self) -> ControllerPoint {
+        let count = self.vertices.len() as f32;
+        ControllerPoint {
+            x: self.vertices.iter().map(|vertex| vertex.x).sum::<f32>() / count,
+            y: self.vertices.iter().map(|vertex| vertex.y).sum::<f32>() / count
+        }
+    }
+    /// Each edge as its start vertex and outward unit normal.
+    fn edges(&self) -> Vec<(ControllerPoint, Normal)> {
+        let center = self.center();
+        (0..self.vertices.len()).map(|index| {
+            let start = self.vertices[index];
+            let end = self.vertices[(index + 1) % self.vertices.len()];
+            let (edge_x, edge_y) = (end.x - start.x, end.y - start.y);
+            let length = (edge_x.powi(2) + edge_y.powi(2)).sqrt().max(SWEEP_EPSILON);
+            let mut normal = (edge_y / length, -edge_x / length);
+
+            if normal.0 * (center.x - start.x) + normal.1 * (center.y - start.y) > 0.0 {
+                normal = (-normal.0, -normal.1);
+            }
+            (start, normal)
+        }).collect()
+    }
+    /// How far the point is from the obstacle's outline, 0.0 when inside.
+    pub(super) fn distance_to(&self, point: ControllerPoint) -> f32 {
+        if self.edges().iter().all(|(start, normal)| normal.0 * (point.x - start.x) + normal.1 * (point.y - start.y) <= 0.0) {
+            return 0.0;
+        }
+        (0..self.vertices.len()).map(|index| {
+            let start = self.vertices[index];
+            let end = self.vertices[(index + 1) % self.vertices.len()];
+            let (edge_x, edge_y) = (end.x - start.x, end.y - start.y);
+            let length_squared = (edge_x.powi(2) + edge_y.powi(2)).max(SWEEP_EPSILON);
+            let t = (((point.x - start.x) * edge_x + (point.y - start.y) * edge_y) / length_squared).clamp(0.0, 1.0);
+            ((start.x + edge_x * t - point.x).powi(2) + (start.y + edge_y * t - point.y).powi(2)).sqrt()
+        }).fold(f32::MAX, f32::min)
+    }
+    /// Separating axis test against an axis aligned box. Returns the push (unit direction and depth)
+    /// that moves the box out of the obstacle the shortest way.
+    pub(super) fn check_box_overlap(&self, min: ControllerPoint, size: f32) -> Option<(Normal, f32)> {
+        let corners = [
+            min,
+            ControllerPoint { x: min.x + size, y: min.y },
+            ControllerPoint { x: min.x + size, y: min.y + size },
+            ControllerPoint { x: min.x, y: min.y + size }
+        ];
+        let box_center = ControllerPoint { x: min.x + size / 2.0, y: min.y + size / 2.0 };
+        let obstacle_center = self.center();
+
+        let mut axes = vec![(1.0, 0.0), (0.0, 1.0)];
+        axes.extend(self.edges().into_iter().map(|(_, normal)| normal));
+
+        let mut smallest: Option<(Normal, f32)> = None;
+        for axis in axes {
+            let project = |point: &ControllerPoint| point.x * axis.0 + point.y * axis.1;
+            let box_min = corners.iter().map(project).fold(f32::MAX, f32::min);
+            let box_max = corners.iter().map(project).fold(f32::MIN, f32::max);
+            let obstacle_min = self.vertices.iter().map(project).fold(f32::MAX, f32::min);
+            let obstacle_max = self.vertices.iter().map(project).fold(f32::MIN, f32::max);
+
+            let depth = box_max.min(obstacle_max) - box_min.max(obstacle_min);
+            if depth <= 0.0 {
+                return None;
+            }
+            if smallest.is_none_or(|(_, smallest_depth)| depth < smallest_depth) {
+                let away = project(&box_center) - project(&obstacle_center);
+                let direction = if away < 0.0 { (-axis.0, -axis.1) } else { axis };
+                smallest = Some((direction, depth));
+            }
+        }
+        smallest
+    }
+    /// Clips the segment against every side of the obstacle grown by `radius` and its top.
+    /// Returns the entry and exit fractions of the part of the segment inside.
+    fn clip_segment(&self, from: ControllerPoint, from_height: f32, to: ControllerPoint, to_height: f32, radius: f32) -> Option<(f32, f32, Option<Normal>)> {
+        let direction = (to.x - from.x, to.y - from.y);
+        let mut t_enter: f32 = 0.0;
+        let mut t_exit: f32 = 1.0;
+        let mut entry_normal = None;
+
+        for (start, normal) in self.edges() {
+            let distance = normal.0 * (from.x - start.x) + normal.1 * (from.y - start.y) - radius;
+            let approach = normal.0 * direction.0 + normal.1 * direction.1;
+
+            if approach.abs() < SWEEP_EPSILON {
+                if distance > 0.0 {
+                    return None;
+                }
+                continue;
+            }
+            let t = -distance / approach;
+            if approach < 0.0 {
+                if t > t_enter {
+                    t_enter = t;
+                    entry_normal = Some(normal);
+                }
+            } else {
+                t_exit = t_exit.min(t);
+            }
+            if t_enter > t_exit {
+                return None;
+            }
+        }
+
+        let top = self.height + radius;
+        let climb = to_height - from_height;
+        if climb.abs() < SWEEP_EPSILON {
+            if from_height > top {
+                return None;
+            }
+        } else {
+            let t = (top - from_height) / climb;
+            if climb < 0.0 {
+                if t > t_enter {
+                    t_enter = t;
+                    entry_normal = None;
+                }
+            } else {
+                t_exit = t_exit.min(t);
+            }
+            if t_enter > t_exit {
+                return None;
+            }
+        }
+        Some((t_enter, t_exit, entry_normal))
+    }
+    pub(super) fn to_buffer_obstacle(&self) -> ObstacleResponse {
+        let mut obstacle_response = ObstacleResponse::new();
+        obstacle_response.set_id(self.id);
+        obstacle_response.set_shape(self.shape);
+        obstacle_response.set_vertices(RepeatedField::from_vec(self.vertices.iter().map(|vertex| vertex.to_buffer_point()).collect()));
+        obstacle_response.set_height(self.height as i32);
+        obstacle_response.set_material(self.material);
+        obstacle_response.set_hit_points(self.hit_points.unwrap_or(0));
+        obstacle_response.set_max_hit_points(self.max_hit_points);
+        obstacle_response
+    }
+}
+
+/// How the ground under a tank changes its handling, relative to open ground.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Traction {
+    /// Multiplies the acceleration.
+    pub(super) grip: f32,
+    /// Multiplies how much speed the tank loses each tick it coasts.
+    pub(super) friction: f32,
+    /// Multiplies the top speed of tank classes that have one.
+    pub(super) top_speed: f32
+}
+
+impl Traction {
+    pub(super) const OPEN_GROUND: Traction = Traction { grip: 1.0, friction: 1.0, top_speed: 1.0 };
+
+    fn for_surface(surface: SurfaceType) -> Self {
+        match surface {
+            SurfaceType::mud => Traction { grip: 0.6, friction: 2.5, top_speed: 0.5 },
+            SurfaceType::ice => Traction { grip: 0.3, friction: 0.15, top_speed: 1.2 },
+            SurfaceType::road => Traction { grip: 1.2, friction: 1.0, top_speed: 1.4 },
+            _ => Traction::OPEN_GROUND
+        }
+    }
+}
+
+/// A convex patch of ground with its own surface.
+#[derive(Debug, Clone)]
+pub(super) struct TerrainZone {
+    pub(super) id: i32,
+    pub(super) surface: SurfaceType,
+    pub(super) vertices: Vec<ControllerPoint>
+}
+
+impl TerrainZone {
+    pub(super) fn rectangle(id: i32, surface: SurfaceType, x: f32, y: f32, width: f32, depth: f32) -> Self {
+        TerrainZone {
+            id,
+            surface,
+            vertices: vec![
+                ControllerPoint { x, y },
+                ControllerPoint { x: x + width, y },
+                ControllerPoint { x: x + width, y: y + depth },
+                ControllerPoint { x, y: y + depth }
+            ]
+        }
+    }
+    /// Inside when the point is on the same side of every edge, whichever way the corners wind.
+    fn contains(&self, point: ControllerPoint) -> bool {
+        let sides: Vec<f32> = (0..self.vertices.len()).map(|index| {
+            let start = self.vertices[index];
+            let end = self.vertices[(index + 1) % self.vertices.len()];
+            (end.x - start.x) * (point.y - start.y) - (end.y - start.y) * (point.x - start.x)
+        }).collect();
+        sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+    }
+    pub(super) fn to_buffer_terrain_zone(&self) -> TerrainZoneResponse {
+        let mut terrain_zone_response = TerrainZoneResponse::new();
+        terrain_zone_response.set_id(self.id);
+        terrain_zone_response.set_surface(self.surface);
+        terrain_zone_response.set_vertices(RepeatedField::from_vec(self.vertices.iter().map(|vertex| vertex.to_buffer_point()).collect()));
+        terrain_zone_response
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct SpawnPoint {
+    pub(super) name: String,
+    /// Where the center of the tank goes.
+    pub(super) position: ControllerPoint
+}
+
+impl SpawnPoint {
+    pub(super) fn to_buffer_spawn_point(&self) -> SpawnPointResponse {
+        let mut spawn_point_response = SpawnPointResponse::new();
+        spawn_point_response.set_name(self.name.clone());
+        spawn_point_response.set_position(self.position.to_buffer_point());
+        spawn_point_response
+    }
+}
+
+/// Where a team keeps its flag in capture the flag.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Base {
+    pub(super) team: Team,
+    pub(super) position: ControllerPoint
+}
+
+impl Base {
+    pub(super) fn to_buffer_base(self) -> BaseResponse {
+        let mut base_response = BaseResponse::new();
+        base_response.set_team(self.team);
+        base_response.set_position(self.position.to_buffer_point());
+        base_response
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Arena {
+    pub(super) id: String,
+    pub(super) name: String,
+    pub(super) width: i32,
+    pub(super) height: i32,
+    /// What the layout was generated from, `None` for hand authored ones.
+    pub(super) seed: Option<u64>,
+    pub(super) obstacles: Vec<Obstacle>,
+    pub(super) spawn_points: Vec<SpawnPoint>,
+    /// Where power ups appear, anywhere free when empty.
+    pub(super) pickup_points: Vec<ControllerPoint>,
+    /// Later zones are laid over earlier ones.
+    pub(super) terrain: Vec<TerrainZone>,
+    /// Only hand authored maps have them, see `base_of` for the rest.
+    pub(super) bases: Vec<Base>,
+    /// Where king of the hill puts its control zone, in rotation order.
+    pub(super) hills: Vec<ControllerPoint>
+}
+
+impl Arena {
+    /// The built in layout used when no map files are loaded: a block of cover in front of each
+    /// corner, low walls on the long sides and a tall diamond in the middle.
+    pub(super) fn default_layout(width: i32, height: i32) -> Self {
+        let (w, h) = (width as f32, height as f32);
+        let (center_x, center_y) = (w / 2.0, h / 2.0);
+        Arena {
+            id: "default".to_string(),
+            name: "Default".to_string(),
+            width,
+            height,
+            seed: None,
+            spawn_points: Vec::new(),
+            pickup_points: Vec::new(),
+            terrain: Vec::new(),
+            bases: Vec::new(),
+            hills: Vec::new(),
+            obstacles: vec![
+                Obstacle::rectangle(1, w * 0.2, h * 0.2, 80.0, 80.0, 60.0),
+                Obstacle::rectangle(2, w * 0.8 - 80.0, h * 0.2, 80.0, 80.0, 60.0),
+                Obstacle::rectangle(3, w * 0.2, h * 0.8 - 80.0, 80.0, 80.0, 60.0),
+                Obstacle::rectangle(4, w * 0.8 - 80.0, h * 0.8 - 80.0, 80.0, 80.0, 60.0),
+                Obstacle::rectangle(5, center_x - 150.0, h * 0.1, 300.0, 20.0, 30.0),
+                Obstacle::rectangle(6, center_x - 150.0, h * 0.9 - 20.0, 300.0, 20.0, 30.0),
+                Obstacle::polygon(7, vec![
+                    ControllerPoint { x: center_x, y: center_y - 90.0 },
+                    ControllerPoint { x: center_x + 70.0, y: center_y },
+                    ControllerPoint { x: center_x, y: center_y + 90.0 },
+                    ControllerPoint { x: center_x - 70.0, y: center_y }
+                ], 100.0)
+            ]
+        }
+    }
+    /// The map's base for `team`. Arenas without one use the spawn point farthest out on the
+    /// team's side, red on the left and blue on the right, or the middle of that side's edge.
+    pub(super) fn base_of(&self, team: Team) -> ControllerPoint {
+        if let Some(base) = self.bases.iter().find(|base| base.team == team) {
+            return base.position;
+        }
+        let outwards = |point: &ControllerPoint| if team == Team::blue { point.x } else { -point.x };
+        self.spawn_points.iter()
+            .map(|spawn_point| spawn_point.position)
+            .max_by(|a, b| outwards(a).total_cmp(&outwards(b)))
+            .unwrap_or_else(|| ControllerPoint {
+                x: self.width as f32 * if team == Team::blue { 0.9 } else { 0.1 },
+                y: self.height as f32 / 2.0
+            })
+    }
+    /// Traction of the topmost terrain zone under the point.
+    pub(super) fn traction_at(&self, point: ControllerPoint) -> Traction {
+        self.terrain.iter().rev()
+            .find(|zone| zone.contains(point))
+            .map_or(Traction::OPEN_GROUND, |zone| Traction::for_surface(zone.surface))
+    }
+    pub(super) fn is_out_of_bounds(&self, position: ControllerPoint) -> bool {
+        position.x < 0.0 || position.x > self.width as f32 || position.y < 0.0 || position.y > self.height as f32
+    }
+    pub(super) fn check_box_collision(&self, min: ControllerPoint, size: f32) -> bool {
+        self.obstacles.iter().any(|obstacle| obstacle.check_box_overlap(min, size).is_some())
+    }
+    /// Random top left corner for a box of `size` that is inside the arena and clear of obstacles.
+    /// A box bigger than the arena is put in the top left corner.
+    pub(super) fn random_free_point(&self, size: f32) -> ControllerPoint {
+        let (height_bounds, width_bounds) = ((self.height - size as i32).max(0), (self.width - size as i32).max(0));
+        let mut point = ControllerPoint::random_point(height_bounds, width_bounds);
+        for _ in 0..FREE_POINT_ATTEMPTS {
+            if !self.check_box_collision(point, size) {
+                break;
+            }
+            point = ControllerPoint::random_point(height_bounds, width_bounds);
+        }
+        point
+    }
+    /// Sweeps a sphere of `radius` along the segment and returns the first obstacle it touches.
+    pub(super) fn sweep(&self, from: ControllerPoint, from_height: f32, to: ControllerPoint, to_height: f32, radius: f32) -> Option<SweepHit> {
+        self.obstacles.iter()
+            .filter_map(|obstacle| obstacle.clip_segment(from, from_height, to, to_height, radius))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(t, _, normal)| {
+                let t_before_contact = (t - SWEEP_EPSILON).max(0.0);
+                SweepHit {
+                    t,
+                    position: ControllerPoint {
+                        x: from.x + (to.x - from.x) * t_before_contact,
+                        y: from.y + (to.y - from.y) * t_before_contact
+                    },
+                    normal
+                }
+            })
+    }
+    /// False when an obstacle stands between the two points at `height` above the ground,
+    /// which is what lets tanks take cover from explosions. An obstacle the line starts
+    /// inside of, like one a shell landed on top of, doesn't block it.
+    pub(super) fn has_line_of_sight(&self, from: ControllerPoint, to: ControllerPoint, height: f32) -> bool {
+        !self.obstacles.iter()
+            .filter_map(|obstacle| obstacle.clip_segment(from, height, to, height, 0.0))
+            .any(|(t_enter, t_exit, _)| t_enter > 0.0 && t_exit - t_enter > SWEEP_EPSILON)
+    }
+}
@@ -0,0 +1,214 @@
+use super::arena::Arena;
+use super::classes::TankStats;
+use super::spawns::{self, SpawnStrategy, Threat};
+use super::weapons;
+use super::{ControllerPoint, Player, PlayerInput};
+use crate::{AbilityType, PlayerInGameStatus, TankClass, Team};
+use rand::{thread_rng, Rng};
+use std::collections::hash_map::HashMap;
+
+/// Degrees off target the cannon is still fired at.
+const FIRING_ARC: f32 = 6.0;
+/// Distance a bot tank keeps from its target, far enough for its shells to arc over cover.
+const PREFERRED_RANGE: f32 = 320.0;
+/// How far from the preferred range a bot tank lets its target get before it drives.
+const RANGE_SLACK: f32 = 80.0;
+/// Shells are loaded for up to this fraction off the real distance, so bots miss now and then.
+const POWER_SPREAD: f32 = 0.08;
+/// Ticks of driving without getting anywhere before a bot tries to go around.
+const STUCK_TICKS: i32 = 30;
+const DETOUR_TICKS: i32 = 45;
+/// Ticks a moving dummy drives one way before it turns back.
+const PATROL_TICKS: i32 = 120;
+
+/// Direction bits for driving towards each eighth of a turn, starting at 0° and going the same
+/// way as `Player::input_to_angle_hash`.
+const DIRECTIONS: [i32; 8] = [
+    PlayerInput::Right as i32,
+    PlayerInput::Right as i32 | PlayerInput::Down as i32,
+    PlayerInput::Down as i32,
+    PlayerInput::Down as i32 | PlayerInput::Left as i32,
+    PlayerInput::Left as i32,
+    PlayerInput::Left as i32 | PlayerInput::Up as i32,
+    PlayerInput::Up as i32,
+    PlayerInput::Up as i32 | PlayerInput::Right as i32
+];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(super) enum BotKind {
+    Tank,
+    /// Never drives, only turns its cannon.
+    Turret,
+    /// Target practice, never does anything.
+    Dummy,
+    /// Target practice that drives up and down and never shoots.
+    MovingDummy
+}
+
+/// A server controlled tank. It plays through the same input bits a client sends, so its
+/// `Player` drives, shoots and gets hit under the same rules as everyone else's.
+#[derive(Debug)]
+pub(super) struct Bot {
+    kind: BotKind,
+    last_position: Option<ControllerPoint>,
+    /// Ticks it has been trying to drive without moving.
+    stuck: i32,
+    /// Direction bits and ticks left of going around whatever the bot got stuck on.
+    detour: Option<(i32, i32)>,
+    /// Direction bits and ticks left of a moving dummy's way up or down.
+    patrol: (i32, i32),
+    /// Power the cannon is being loaded up to.
+    loading_to: Option<i32>
+}
+
+impl Bot {
+    pub(super) fn new(kind: BotKind) -> Self {
+        Bot {
+            kind,
+            last_position: None,
+            stuck: 0,
+            detour: None,
+            patrol: (if thread_rng().gen() { PlayerInput::Up as i32 } else { PlayerInput::Down as i32 }, PATROL_TICKS),
+            loading_to: None
+        }
+    }
+    /// Input for `me` this tick: turn the cannon on the closest enemy not hidden in smoke, load
+    /// a shell for its distance and, for tanks, drive to where the shells reach it best.
+    pub(super) fn think(&mut self, me: &Player, players: &HashMap<i32, Player>, muzzle_velocity: f32) -> i32 {
+        match self.kind {
+            BotKind::Dummy => return PlayerInput::NoInput as i32,
+            BotKind::MovingDummy if me.is_alive() => return self.patrol(me),
+            BotKind::MovingDummy => return PlayerInput::NoInput as i32,
+            BotKind::Tank | BotKind::Turret => ()
+        }
+        let center = me.center();
+        let target = players.values()
+            .filter(|player| player.id != me.id && player.is_alive() && !player.is_concealed && !player.is_on_team(me.team))
+            .min_by(|a, b| distance(center, a.center()).total_cmp(&distance(center, b.center())));
+        let Some(target) = target.filter(|_| me.is_alive()) else {
+            self.loading_to = None;
+            return PlayerInput::NoInput as i32;
+        };
+
+        let target_center = target.center();
+        let (dx, dy) = (target_center.x - center.x, target_center.y - center.y);
+        let range = distance(center, target_center);
+        // -180 to 180, positive when the target is further along in the `AimPositive` direction.
+        let aim_error = (dy.atan2(dx).to_degrees() - me.cannon_angle + 180.0).rem_euclid(360.0) - 180.0;
+
+        let mut input = Bot::aim(me, aim_error) | self.shoot(me, aim_error, range, muzzle_velocity);
+        if self.kind == BotKind::Tank {
+            input |= self.drive(me, dx, dy, range);
+        }
+        input
+    }
+    /// The cannon turns a degree a tick faster for as long as it is held and stops dead when let
+    /// go, so it is let go before the next step would swing past the target.
+    fn aim(me: &Player, aim_error: f32) -> i32 {
+        if aim_error.abs() < me.delta_a.abs() + 1.0 {
+            return PlayerInput::NoInput as i32;
+        }
+        if aim_error > 0.0 { PlayerInput::AimPositive as i32 } else { PlayerInput::AimNegative as i32 }
+    }
+    fn shoot(&mut self, me: &Player, aim_error: f32, range: f32, muzzle_velocity: f32) -> i32 {
+        let weapon = &me.weapons[me.equipped_weapon];
+        if let Some(power) = self.loading_to {
+            if !weapon.is_loading() {
+                self.loading_to = None;
+                return PlayerInput::NoInput as i32;
+            }
+            if weapon.power_loaded < power {
+                return PlayerInput::NoInput as i32;
+            }
+            self.loading_to = None;
+            return PlayerInput::Fire as i32;
+        }
+        if aim_error.abs() > FIRING_ARC || weapon.is_busy() {
+            return PlayerInput::NoInput as i32;
+        }
+        let spread = thread_rng().gen_range(1.0 - POWER_SPREAD..=1.0 + POWER_SPREAD);
+        let Some(power) = weapons::shell_power_for((range - me.stats.cannon_length) * spread, muzzle_velocity) else {
+            return PlayerInput::NoInput as i32;
+        };
+        self.loading_to = Some(power);
+        PlayerInput::LoadCannon as i32
+    }
+    /// Drives one way until it has gone on for a while or runs into something, then turns back.
+    fn patrol(&mut self, me: &Player) -> i32 {
+        let moved = self.last_position.map_or(f32::MAX, |last_position| distance(last_position, me.position));
+        self.last_position = Some(me.position);
+        self.stuck = if moved < 0.5 { self.stuck + 1 } else { 0 };
+
+        let (direction, ticks_left) = self.patrol;
+        if ticks_left > 1 && self.stuck < STUCK_TICKS {
+            self.patrol = (direction, ticks_left - 1);
+            return direction;
+        }
+        self.stuck = 0;
+        let turned = direction ^ (PlayerInput::Up as i32 | PlayerInput::Down as i32);
+        self.patrol = (turned, PATROL_TICKS);
+        turned
+    }
+    /// Closes in on a target that is out of range and backs off one that is too close.
+    fn drive(&mut self, me: &Player, dx: f32, dy: f32, range: f32) -> i32 {
+        let moved = self.last_position.map_or(f32::MAX, |last_position| distance(last_position, me.position));
+        self.last_position = Some(me.position);
+
+        if let Some((direction, ticks_left)) = self.detour {
+            self.detour = (ticks_left > 1).then_some((direction, ticks_left - 1));
+            return direction;
+        }
+        let direction = if range > PREFERRED_RANGE + RANGE_SLACK {
+            direction_towards(dx, dy)
+        } else if range < PREFERRED_RANGE - RANGE_SLACK {
+            direction_towards(-dx, -dy)
+        } else {
+            self.stuck = 0;
+            return PlayerInput::NoInput as i32;
+        };
+
+        self.stuck = if moved < 0.5 { self.stuck + 1 } else { 0 };
+        if self.stuck < STUCK_TICKS {
+            return direction;
+        }
+        // Off to one side or the other of where it was trying to go.
+        self.stuck = 0;
+        let detour = if thread_rng().gen() { direction_towards(-dy, dx) } else { direction_towards(dy, -dx) };
+        self.detour = Some((detour, DETOUR_TICKS));
+        detour
+    }
+}
+
+/// A bot tank of `tank_class` on `team`'s side of the arena, as far from everyone else as there is room for.
+pub(super) fn spawn(id: i32, tank_class: TankClass, team: Team, armor: f32, players: &HashMap<i32, Player>, arena: &Arena) -> Player {
+    let threats: Vec<Threat> = players.values()
+        .filter(|player| player.player_in_game_status != PlayerInGameStatus::spectating)
+        .map(|player| player.threat_to(Some(team), 1.0))
+        .collect();
+    let position = spawns::choose_spawn(SpawnStrategy::SafeRandom, arena, &threats, TankStats::for_class(tank_class).size, Some(team));
+    let mut bot = Player::new(id, position, armor, AbilityType::empty_15, tank_class, Some(team));
+    bot.is_bot = true;
+    bot
+}
+
+/// Hands every bot's tank the input its bot came up with this tick.
+pub(super) fn drive_all(bots: &mut HashMap<i32, Bot>, players: &mut HashMap<i32, Player>, muzzle_velocity: f32) {
+    let inputs: Vec<(i32, i32)> = bots.iter_mut()
+        .filter_map(|(id, bot)| players.get(id).map(|me| (*id, bot.think(me, players, muzzle_velocity))))
+        .collect();
+    for (id, input) in inputs {
+        if let Some(player) = players.get_mut(&id) {
+            player.input(input);
+        }
+    }
+}
+
+fn distance(from: ControllerPoint, to: ControllerPoint) -> f32 {
+    ((to.x - from.x).powi(2) + (to.y - from.y).powi(2)).sqrt()
+}
+
+/// The one or two direction bits that drive closest to along (`dx`, `dy`).
+fn direction_towards(dx: f32, dy: f32) -> i32 {
+    let eighth = ((dy.atan2(dx).to_degrees().rem_euclid(360.0) + 22.5) / 45.0) as usize % 8;
+    DIRECTIONS[eighth]
+}
@@ -0,0 +1,60 @@
+use super::{ANGLE_EASING_FACTOR, CANNON_LENGTH, PLAYER_SIZE};
+use crate::TankClass;
+
+/// Physics and weapon stats that depend on the tank class. Medium tanks keep the stats every
+/// tank had before there were classes.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct TankStats {
+    /// Acceleration per tick while driving.
+    pub(super) motor: f32,
+    /// Fraction of its speed the tank keeps each tick it coasts.
+    pub(super) brakes: f32,
+    /// Fastest the tank drives on its own, in px per tick. `None` leaves it uncapped, like every
+    /// tank was before there were classes.
+    pub(super) top_speed: Option<f32>,
+    /// How much of the remaining turn the hull makes each tick.
+    pub(super) turn_easing: f32,
+    pub(super) size: f32,
+    pub(super) cannon_length: f32,
+    /// Fraction of incoming damage the hull absorbs, on top of the match's armor setting.
+    pub(super) armor: f32,
+    /// Multiplies every weapon's reload time.
+    pub(super) reload_factor: f32
+}
+
+impl TankStats {
+    pub(super) fn for_class(tank_class: TankClass) -> Self {
+        match tank_class {
+            TankClass::light => TankStats {
+                motor: 1.5,
+                brakes: 0.85,
+                top_speed: Some(10.0),
+                turn_easing: 0.35,
+                size: 32.0,
+                cannon_length: 32.0,
+                armor: 0.0,
+                reload_factor: 0.8
+            },
+            TankClass::heavy => TankStats {
+                motor: 0.75,
+                brakes: 0.93,
+                top_speed: Some(5.0),
+                turn_easing: 0.12,
+                size: 52.0,
+                cannon_length: 48.0,
+                armor: 0.3,
+                reload_factor: 1.3
+            },
+            _ => TankStats {
+                motor: 1.0,
+                brakes: 0.9,
+                top_speed: None,
+                turn_easing: ANGLE_EASING_FACTOR,
+                size: PLAYER_SIZE,
+                cannon_length: CANNON_LENGTH,
+                armor: 0.0,
+                reload_factor: 1.0
+            }
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use super::arena::{Arena, Obstacle, SpawnPoint, MIN_ARENA_SIZE};
+use super::ControllerPoint;
+use crate::{GeneratorSettingsInfo, ObstacleMaterial};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+/// Obstacles are laid out on a grid of cells at least this big, two tank widths so a tank
+/// always fits through a free cell.
+const MIN_CELL_SIZE: f32 = 80.0;
+/// Gap kept between an obstacle and the edge of its cells.
+const MIN_CELL_INSET: f32 = 8.0;
+const MAX_CELL_INSET: f32 = 20.0;
+const MAX_DENSITY: f32 = 0.6;
+/// Biggest generated arena, a big open field.
+const MAX_ARENA_SIZE: i32 = 4000;
+const PLACEMENT_ATTEMPTS_PER_CELL: usize = 4;
+const SPAWN_POINT_COUNT: usize = 8;
+const OBSTACLE_HEIGHTS: [f32; 3] = [30.0, 60.0, 100.0];
+/// Chance a block turns out to be a crate, or a wall brick, that explosions can break.
+const DESTRUCTIBLE_CHANCE: f64 = 0.35;
+const BARREL_RADIUS: f32 = 18.0;
+const BARREL_HEIGHT: f32 = 40.0;
+pub(super) const GENERATED_ARENA_ID: &str = "generated";
+
+/// How generated arenas are laid out.
+#[derive(Debug, Clone, Copy)]
+pub struct GeneratorSettings {
+    pub width: i32,
+    pub height: i32,
+    /// Fraction of the arena covered by obstacles, capped at 0.6.
+    pub density: f32
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        GeneratorSettings {
+            width: 1200,
+            height: 800,
+            density: 0.2
+        }
+    }
+}
+
+impl GeneratorSettings {
+    /// Settings a client sent from the lobby. Anything it left unset keeps its default, the rest
+    /// is kept to sizes and densities that can be generated.
+    pub fn from_buffer(settings: &GeneratorSettingsInfo) -> Self {
+        let defaults = GeneratorSettings::default();
+        GeneratorSettings {
+            width: if settings.has_width() { settings.get_width().clamp(MIN_ARENA_SIZE, MAX_ARENA_SIZE) } else { defaults.width },
+            height: if settings.has_height() { settings.get_height().clamp(MIN_ARENA_SIZE, MAX_ARENA_SIZE) } else { defaults.height },
+            density: if settings.has_density() { settings.get_density().clamp(0.0, MAX_DENSITY) } else { defaults.density }
+        }
+    }
+    pub(super) fn to_buffer_generator_settings(self) -> GeneratorSettingsInfo {
+        let mut settings = GeneratorSettingsInfo::new();
+        settings.set_width(self.width);
+        settings.set_height(self.height);
+        settings.set_density(self.density);
+        settings
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Piece {
+    Block { columns: usize, rows: usize },
+    Wall { columns: usize, rows: usize },
+    Diamond,
+    Barrel
+}
+
+impl Piece {
+    fn random(rng: &mut StdRng) -> Self {
+        match rng.gen_range(0..7) {
+            0 | 1 => Piece::Block { columns: 1, rows: 1 },
+            2 => Piece::Block { columns: 2, rows: 2 },
+            3 => Piece::Wall { columns: rng.gen_range(2..=3), rows: 1 },
+            4 => Piece::Wall { columns: 1, rows: rng.gen_range(2..=3) },
+            5 => Piece::Diamond,
+            _ => Piece::Barrel
+        }
+    }
+    fn size(&self) -> (usize, usize) {
+        match self {
+            Piece::Block { columns, rows } | Piece::Wall { columns, rows } => (*columns, *rows),
+            Piece::Diamond | Piece::Barrel => (1, 1)
+        }
+    }
+}
+
+struct Grid {
+    columns: usize,
+    rows: usize,
+    cell_width: f32,
+    cell_height: f32,
+    blocked: Vec<bool>
+}
+
+impl Grid {
+    fn new(width: i32, height: i32) -> Self {
+        let columns = ((width as f32 / MIN_CELL_SIZE) as usize).max(1);
+        let rows = ((height as f32 / MIN_CELL_SIZE) as usize).max(1);
+        Grid {
+            columns,
+            rows,
+            cell_width: width as f32 / columns as f32,
+            cell_height: height as f32 / rows as f32,
+            blocked: vec![false; columns * rows]
+        }
+    }
+    fn cells(&self, column: usize, row: usize, columns: usize, rows: usize) -> Vec<usize> {
+        (row..row + rows).flat_map(|row| (column..column + columns).map(move |column| row * self.columns + column)).collect()
+    }
+    fn cell_center(&self, index: usize) -> ControllerPoint {
+        ControllerPoint {
+            x: ((index % self.columns) as f32 + 0.5) * self.cell_width,
+            y: ((index / self.columns) as f32 + 0.5) * self.cell_height
+        }
+    }
+    fn free_cells(&self) -> Vec<usize> {
+        (0..self.blocked.len()).filter(|index| !self.blocked[*index]).collect()
+    }
+    /// Whether every free cell can be reached from every other one moving up, down, left and right.
+    fn is_connected(&self) -> bool {
+        let free_cells = self.free_cells();
+        let Some(&start) = free_cells.first() else {
+            return false;
+        };
+        let mut visited = vec![false; self.blocked.len()];
+        let mut queue = VecDeque::from([start]);
+        visited[start] = true;
+        let mut reached = 0;
+
+        while let Some(index) = queue.pop_front() {
+            reached += 1;
+            let (column, row) = (index % self.columns, index / self.columns);
+            let neighbours = [
+                (column > 0).then(|| index - 1),
+                (column + 1 < self.columns).then(|| index + 1),
+                (row > 0).then(|| index - self.columns),
+                (row + 1 < self.rows).then(|| index + self.columns)
+            ];
+            for neighbour in neighbours.into_iter().flatten() {
+                if !visited[neighbour] && !self.blocked[neighbour] {
+                    visited[neighbour] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        reached == free_cells.len()
+    }
+}
+
+/// Builds an arena from the seed, the same seed and settings always give the same layout.
+///
+/// Obstacles only ever fill whole grid cells and a piece is thrown away if it would cut the
+/// free cells in two, so every open part of the arena can be driven to. Spawn points go in
+/// free cells as far from each other as the layout allows.
+pub(super) fn generate(settings: &GeneratorSettings, seed: u64) -> Arena {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut grid = Grid::new(settings.width, settings.height);
+    let target_blocked = (grid.blocked.len() as f32 * settings.density.clamp(0.0, MAX_DENSITY)) as usize;
+    let mut blocked_count = 0;
+    let mut obstacles = Vec::new();
+
+    for _ in 0..grid.blocked.len() * PLACEMENT_ATTEMPTS_PER_CELL {
+        if blocked_count >= target_blocked {
+            break;
+        }
+        let piece = Piece::random(&mut rng);
+        let (columns, rows) = piece.size();
+        if columns > grid.columns || rows > grid.rows {
+            continue;
+        }
+        let column = rng.gen_range(0..=grid.columns - columns);
+        let row = rng.gen_range(0..=grid.rows - rows);
+        let cells = grid.cells(column, row, columns, rows);
+        if cells.iter().any(|cell| grid.blocked[*cell]) {
+            continue;
+        }
+
+        cells.iter().for_each(|cell| grid.blocked[*cell] = true);
+        if !grid.is_connected() {
+            cells.iter().for_each(|cell| grid.blocked[*cell] = false);
+            continue;
+        }
+        blocked_count += cells.len();
+
+        let id = obstacles.len() as i32 + 1;
+        let inset = rng.gen_range(MIN_CELL_INSET..=MAX_CELL_INSET);
+        let height = *OBSTACLE_HEIGHTS.choose(&mut rng).unwrap_or(&OBSTACLE_HEIGHTS[0]);
+        let x = column as f32 * grid.cell_width + inset;
+        let y = row as f32 * grid.cell_height + inset;
+        let width = columns as f32 * grid.cell_width - inset * 2.0;
+        let depth = rows as f32 * grid.cell_height - inset * 2.0;
+        let destructible = rng.gen_bool(DESTRUCTIBLE_CHANCE);
+
+        obstacles.push(match piece {
+            Piece::Block { .. } if destructible => Obstacle::rectangle(id, x, y, width, depth, height).with_material(ObstacleMaterial::wooden_crate, None),
+            Piece::Block { .. } => Obstacle::rectangle(id, x, y, width, depth, height),
+            Piece::Wall { columns, .. } => {
+                let wall = if columns > 1 {
+                    let thickness = rng.gen_range(20.0..=depth.max(20.0));
+                    Obstacle::rectangle(id, x, y + (depth - thickness) / 2.0, width, thickness, height)
+                } else {
+                    let thickness = rng.gen_range(20.0..=width.max(20.0));
+                    Obstacle::rectangle(id, x + (width - thickness) / 2.0, y, thickness, depth, height)
+                };
+                match destructible {
+                    true => wall.with_material(ObstacleMaterial::brick_wall, None),
+                    false => wall
+                }
+            }
+            Piece::Barrel => {
+                let (center_x, center_y) = (x + width / 2.0, y + depth / 2.0);
+                let vertices = (0..8).map(|corner| {
+                    let angle = (22.5 + 45.0 * corner as f32).to_radians();
+                    ControllerPoint { x: center_x + BARREL_RADIUS * angle.cos(), y: center_y + BARREL_RADIUS * angle.sin() }
+                }).collect();
+                Obstacle::polygon(id, vertices, BARREL_HEIGHT).with_material(ObstacleMaterial::explosive_barrel, None)
+            }
+            Piece::Diamond => {
+                let (center_x, center_y) = (x + width / 2.0, y + depth / 2.0);
+                Obstacle::polygon(id, vec![
+                    ControllerPoint { x: center_x, y },
+                    ControllerPoint { x: x + width, y: center_y },
+                    ControllerPoint { x: center_x, y: y + depth },
+                    ControllerPoint { x, y: center_y }
+                ], height)
+            }
+        });
+    }
+
+    Arena {
+        id: GENERATED_ARENA_ID.to_string(),
+        name: format!("Generated #{}", seed),
+        width: settings.width,
+        height: settings.height,
+        seed: Some(seed),
+        obstacles,
+        spawn_points: spread_spawn_points(&grid),
+        pickup_points: Vec::new(),
+        terrain: Vec::new(),
+        bases: Vec::new(),
+        hills: Vec::new()
+    }
+}
+
+/// Farthest point sampling over the free cells, starting with the one farthest from the middle.
+fn spread_spawn_points(grid: &Grid) -> Vec<SpawnPoint> {
+    let middle = ControllerPoint { x: grid.columns as f32 * grid.cell_width / 2.0, y: grid.rows as f32 * grid.cell_height / 2.0 };
+    let distance = |a: ControllerPoint, b: ControllerPoint| (a.x - b.x).powi(2) + (a.y - b.y).powi(2);
+    let free_positions: Vec<ControllerPoint> = grid.free_cells().into_iter().map(|cell| grid.cell_center(cell)).collect();
+
+    let mut spawn_positions: Vec<ControllerPoint> = Vec::new();
+    while spawn_positions.len() < SPAWN_POINT_COUNT.min(free_positions.len()) {
+        let next = free_positions.iter().copied().max_by(|a, b| {
+            let score = |position: ControllerPoint| match spawn_positions.is_empty() {
+                true => distance(position, middle),
+                false => spawn_positions.iter().map(|spawn| distance(position, *spawn)).fold(f32::MAX, f32::min)
+            };
+            score(*a).total_cmp(&score(*b))
+        });
+        match next {
+            Some(position) => spawn_positions.push(position),
+            None => break
+        }
+    }
+
+    spawn_positions.into_iter().enumerate().map(|(index, position)| SpawnPoint {
+        name: format!("spawn {}", index + 1),
+        position
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamelogic::PLAYER_SIZE;
+
+    const SEEDS: std::ops::Range<u64> = 0..20;
+
+    fn dense(width: i32, height: i32) -> GeneratorSettings {
+        GeneratorSettings { width, height, density: MAX_DENSITY }
+    }
+
+    /// Grid of the arena with every cell whose center an obstacle covers blocked. Pieces fill
+    /// whole cells and are centered in them, so this is the grid the layout was built on.
+    fn grid_of(arena: &Arena) -> Grid {
+        let mut grid = Grid::new(arena.width, arena.height);
+        for cell in 0..grid.blocked.len() {
+            let center = grid.cell_center(cell);
+            grid.blocked[cell] = arena.obstacles.iter().any(|obstacle| obstacle.distance_to(center) == 0.0);
+        }
+        grid
+    }
+
+    fn layout(arena: &Arena) -> Vec<Vec<(f32, f32)>> {
+        let mut layout: Vec<Vec<(f32, f32)>> = arena.obstacles.iter().map(|obstacle| obstacle.vertices.iter().map(|vertex| (vertex.x, vertex.y)).collect()).collect();
+        layout.push(arena.spawn_points.iter().map(|spawn| (spawn.position.x, spawn.position.y)).collect());
+        layout
+    }
+
+    #[test]
+    fn grid_split_by_a_full_column_is_not_connected() {
+        let mut grid = Grid::new(400, 400);
+        for row in 0..grid.rows {
+            grid.blocked[row * grid.columns + 2] = true;
+        }
+        assert!(!grid.is_connected());
+
+        grid.blocked[2] = false;
+        assert!(grid.is_connected());
+    }
+
+    #[test]
+    fn generated_arenas_are_connected() {
+        for seed in SEEDS {
+            let arena = generate(&dense(1200, 800), seed);
+            let grid = grid_of(&arena);
+            assert!(grid.blocked.iter().any(|blocked| *blocked), "seed {seed} placed nothing");
+            assert!(grid.is_connected(), "seed {seed} cut the arena in two");
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_layout() {
+        let settings = GeneratorSettings::default();
+        assert_eq!(layout(&generate(&settings, 7)), layout(&generate(&settings, 7)));
+        assert_ne!(layout(&generate(&settings, 7)), layout(&generate(&settings, 8)));
+    }
+
+    #[test]
+    fn spawn_points_are_clear_of_obstacles() {
+        for seed in SEEDS {
+            let arena = generate(&dense(800, 600), seed);
+            assert_eq!(arena.spawn_points.len(), SPAWN_POINT_COUNT);
+            for spawn in &arena.spawn_points {
+                let corner = ControllerPoint { x: spawn.position.x - PLAYER_SIZE / 2.0, y: spawn.position.y - PLAYER_SIZE / 2.0 };
+                assert!(!arena.is_out_of_bounds(spawn.position), "seed {seed} spawns outside at {:?}", spawn.position);
+                assert!(!arena.check_box_collision(corner, PLAYER_SIZE), "seed {seed} spawns in an obstacle at {:?}", spawn.position);
+            }
+        }
+    }
+}
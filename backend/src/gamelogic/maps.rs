@@ -0,0 +1,394 @@
+use super::arena::{Arena, Base, Obstacle, SpawnPoint, TerrainZone, MIN_ARENA_SIZE};
+use super::generator::GENERATED_ARENA_ID;
+use super::ControllerPoint;
+use crate::{ObstacleMaterial, SurfaceType, Team};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// A hand authored arena loaded from a JSON map file.
+///
+/// ```json
+/// {
+///     "id": "crossroads",
+///     "name": "Crossroads",
+///     "width": 1200,
+///     "height": 800,
+///     "obstacles": [
+///         { "shape": "rectangle", "x": 240, "y": 160, "width": 80, "depth": 80, "height": 60 },
+///         { "shape": "polygon", "vertices": [[600, 310], [670, 400], [600, 490], [530, 400]], "height": 100 },
+///         { "shape": "rectangle", "x": 400, "y": 600, "width": 40, "depth": 40, "height": 40, "material": "wooden_crate" }
+///     ],
+///     "spawn_points": [{ "name": "north west", "x": 60, "y": 60 }],
+///     "pickup_points": [[600, 150], [600, 650]],
+///     "bases": [{ "team": "red", "x": 130, "y": 300 }, { "team": "blue", "x": 1070, "y": 500 }],
+///     "hills": [[600, 200], [600, 600]],
+///     "terrain": [
+///         { "shape": "rectangle", "surface": "road", "x": 0, "y": 380, "width": 1200, "depth": 40 },
+///         { "shape": "polygon", "surface": "mud", "vertices": [[100, 600], [300, 600], [250, 750]] }
+///     ]
+/// }
+/// ```
+///
+/// Arenas are at least 400 by 400 and everything on them has to lie inside.
+/// Obstacle `height` is how tall it stands, `depth` is the rectangle's extent along y.
+/// `material` is one of `concrete` (the default, indestructible), `wooden_crate`, `brick_wall`
+/// or `explosive_barrel`, and `hit_points` overrides how much the material can take.
+/// Spawn points are the center of the tank. Power ups appear on the pickup points, or anywhere
+/// free if a map has none. Bases are where capture the flag keeps each team's flag, `red` or
+/// `blue`, at most one per team. King of the hill moves its zone through the hills in order.
+/// Terrain zones are `mud`, `ice` or `road`, where they overlap the one listed last wins.
+/// Polygons, obstacles and terrain alike, have to be convex. Ids are unique and `generated` is
+/// taken by the generated arena.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MapDefinition {
+    id: String,
+    name: String,
+    width: i32,
+    height: i32,
+    #[serde(default)]
+    obstacles: Vec<ObstacleDefinition>,
+    #[serde(default)]
+    spawn_points: Vec<SpawnPointDefinition>,
+    #[serde(default)]
+    pickup_points: Vec<[f32; 2]>,
+    #[serde(default)]
+    bases: Vec<BaseDefinition>,
+    #[serde(default)]
+    hills: Vec<[f32; 2]>,
+    #[serde(default)]
+    terrain: Vec<TerrainDefinition>
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+enum ObstacleDefinition {
+    Rectangle {
+        x: f32,
+        y: f32,
+        width: f32,
+        depth: f32,
+        height: f32,
+        #[serde(default)]
+        material: MaterialDefinition,
+        hit_points: Option<i32>
+    },
+    Polygon {
+        vertices: Vec<[f32; 2]>,
+        height: f32,
+        #[serde(default)]
+        material: MaterialDefinition,
+        hit_points: Option<i32>
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+enum TerrainDefinition {
+    Rectangle { surface: SurfaceDefinition, x: f32, y: f32, width: f32, depth: f32 },
+    Polygon { surface: SurfaceDefinition, vertices: Vec<[f32; 2]> }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SurfaceDefinition {
+    Mud,
+    Ice,
+    Road
+}
+
+impl SurfaceDefinition {
+    fn to_surface(self) -> SurfaceType {
+        match self {
+            SurfaceDefinition::Mud => SurfaceType::mud,
+            SurfaceDefinition::Ice => SurfaceType::ice,
+            SurfaceDefinition::Road => SurfaceType::road
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MaterialDefinition {
+    #[default]
+    Concrete,
+    WoodenCrate,
+    BrickWall,
+    ExplosiveBarrel
+}
+
+impl MaterialDefinition {
+    fn to_material(self) -> ObstacleMaterial {
+        match self {
+            MaterialDefinition::Concrete => ObstacleMaterial::concrete,
+            MaterialDefinition::WoodenCrate => ObstacleMaterial::wooden_crate,
+            MaterialDefinition::BrickWall => ObstacleMaterial::brick_wall,
+            MaterialDefinition::ExplosiveBarrel => ObstacleMaterial::explosive_barrel
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TeamDefinition {
+    Red,
+    Blue
+}
+
+impl TeamDefinition {
+    fn to_team(self) -> Team {
+        match self {
+            TeamDefinition::Red => Team::red,
+            TeamDefinition::Blue => Team::blue
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct BaseDefinition {
+    team: TeamDefinition,
+    x: f32,
+    y: f32
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SpawnPointDefinition {
+    name: String,
+    x: f32,
+    y: f32
+}
+
+impl MapDefinition {
+    /// Loads every `.json` map in the directory, sorted by id so the rotation order is stable.
+    /// Files that fail to parse or validate are skipped and come back as the second list, one
+    /// message per file. Of two maps with the same id the one in the file that sorts first is kept.
+    pub fn load_dir(directory: &Path) -> std::io::Result<(Vec<MapDefinition>, Vec<String>)> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut maps: Vec<MapDefinition> = Vec::new();
+        let mut skipped = Vec::new();
+        for path in paths {
+            let map = fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|contents| serde_json::from_str::<MapDefinition>(&contents).map_err(|error| error.to_string()))
+                .and_then(|map| map.validate().map(|_| map))
+                .and_then(|map| if maps.iter().any(|other| other.id == map.id) {
+                    Err(format!("another map already has the id {}", map.id))
+                } else {
+                    Ok(map)
+                });
+
+            match map {
+                Ok(map) => maps.push(map),
+                Err(error) => skipped.push(format!("Skipping map {}: {}", path.display(), error))
+            }
+        }
+        maps.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok((maps, skipped))
+    }
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    fn validate(&self) -> Result<(), String> {
+        if self.id == GENERATED_ARENA_ID {
+            return Err(format!("the id {} is reserved for generated arenas", GENERATED_ARENA_ID));
+        }
+        if self.width < MIN_ARENA_SIZE || self.height < MIN_ARENA_SIZE {
+            return Err(format!("arena has to be at least {} by {}", MIN_ARENA_SIZE, MIN_ARENA_SIZE));
+        }
+        let inside = |[x, y]: [f32; 2]| (0.0..=self.width as f32).contains(&x) && (0.0..=self.height as f32).contains(&y);
+        for obstacle in self.obstacles.iter() {
+            let hit_points = match obstacle {
+                ObstacleDefinition::Polygon { vertices, hit_points, .. } => {
+                    if vertices.len() < 3 {
+                        return Err("polygons need at least three vertices".to_string());
+                    }
+                    if !vertices.iter().copied().all(inside) {
+                        return Err("obstacles have to be inside the arena".to_string());
+                    }
+                    if !is_convex(vertices) {
+                        return Err("polygons have to be convex".to_string());
+                    }
+                    hit_points
+                }
+                ObstacleDefinition::Rectangle { x, y, width, depth, hit_points, .. } => {
+                    if *width <= 0.0 || *depth <= 0.0 {
+                        return Err("rectangles need a positive width and depth".to_string());
+                    }
+                    if !inside([*x, *y]) || !inside([x + width, y + depth]) {
+                        return Err("obstacles have to be inside the arena".to_string());
+                    }
+                    hit_points
+                }
+            };
+            if hit_points.is_some_and(|hit_points| hit_points <= 0) {
+                return Err("hit points have to be positive".to_string());
+            }
+        }
+        for team in [Team::red, Team::blue] {
+            if self.bases.iter().filter(|base| base.team.to_team() == team).count() > 1 {
+                return Err("only one base per team".to_string());
+            }
+        }
+        for zone in self.terrain.iter() {
+            let corners = match zone {
+                TerrainDefinition::Polygon { vertices, .. } => {
+                    if vertices.len() < 3 {
+                        return Err("terrain polygons need at least three vertices".to_string());
+                    }
+                    if !is_convex(vertices) {
+                        return Err("terrain polygons have to be convex".to_string());
+                    }
+                    vertices.clone()
+                }
+                TerrainDefinition::Rectangle { x, y, width, depth, .. } => vec![[*x, *y], [x + width, y + depth]]
+            };
+            if !corners.into_iter().all(inside) {
+                return Err("terrain zones have to be inside the arena".to_string());
+            }
+        }
+        let points = self.spawn_points.iter().map(|spawn_point| [spawn_point.x, spawn_point.y])
+            .chain(self.pickup_points.iter().copied())
+            .chain(self.bases.iter().map(|base| [base.x, base.y]))
+            .chain(self.hills.iter().copied());
+        for point in points {
+            if !inside(point) {
+                return Err("spawn points, pickup points, bases and hills have to be inside the arena".to_string());
+            }
+        }
+        Ok(())
+    }
+    pub(super) fn to_arena(&self) -> Arena {
+        let obstacles = self.obstacles.iter().enumerate().map(|(index, obstacle)| {
+            let id = index as i32 + 1;
+            match obstacle {
+                ObstacleDefinition::Rectangle { x, y, width, depth, height, material, hit_points } => {
+                    Obstacle::rectangle(id, *x, *y, *width, *depth, *height).with_material(material.to_material(), *hit_points)
+                }
+                ObstacleDefinition::Polygon { vertices, height, material, hit_points } => {
+                    Obstacle::polygon(id, vertices.iter().map(|[x, y]| ControllerPoint { x: *x, y: *y }).collect(), *height)
+                        .with_material(material.to_material(), *hit_points)
+                }
+            }
+        }).collect();
+
+        let spawn_points = self.spawn_points.iter().map(|spawn_point| SpawnPoint {
+            name: spawn_point.name.clone(),
+            position: ControllerPoint { x: spawn_point.x, y: spawn_point.y }
+        }).collect();
+
+        let terrain = self.terrain.iter().enumerate().map(|(index, zone)| {
+            let id = index as i32 + 1;
+            match zone {
+                TerrainDefinition::Rectangle { surface, x, y, width, depth } => TerrainZone::rectangle(id, surface.to_surface(), *x, *y, *width, *depth),
+                TerrainDefinition::Polygon { surface, vertices } => TerrainZone {
+                    id,
+                    surface: surface.to_surface(),
+                    vertices: vertices.iter().map(|[x, y]| ControllerPoint { x: *x, y: *y }).collect()
+                }
+            }
+        }).collect();
+
+        Arena {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            width: self.width,
+            height: self.height,
+            seed: None,
+            obstacles,
+            spawn_points,
+            pickup_points: self.pickup_points.iter().map(|[x, y]| ControllerPoint { x: *x, y: *y }).collect(),
+            terrain,
+            bases: self.bases.iter().map(|base| Base { team: base.team.to_team(), position: ControllerPoint { x: base.x, y: base.y } }).collect(),
+            hills: self.hills.iter().map(|[x, y]| ControllerPoint { x: *x, y: *y }).collect()
+        }
+    }
+}
+
+/// Whether the polygon turns the same way at every corner and goes around only once.
+/// Straight corners are allowed.
+fn is_convex(vertices: &[[f32; 2]]) -> bool {
+    let mut winding = 0.0;
+    let mut turn = 0.0;
+    for (index, [x, y]) in vertices.iter().enumerate() {
+        let [next_x, next_y] = vertices[(index + 1) % vertices.len()];
+        let [after_x, after_y] = vertices[(index + 2) % vertices.len()];
+        let (edge_x, edge_y, next_edge_x, next_edge_y) = (next_x - x, next_y - y, after_x - next_x, after_y - next_y);
+        let cross = edge_x * next_edge_y - edge_y * next_edge_x;
+        if cross * turn < 0.0 {
+            return false;
+        }
+        if cross != 0.0 {
+            turn = cross;
+        }
+        winding += cross.atan2(edge_x * next_edge_x + edge_y * next_edge_y);
+    }
+    turn != 0.0 && (winding.abs() - std::f32::consts::TAU).abs() < 0.01
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<MapDefinition, String> {
+        let map = serde_json::from_str::<MapDefinition>(json).map_err(|error| error.to_string())?;
+        map.validate().map(|_| map)
+    }
+
+    fn with_obstacle(obstacle: &str) -> String {
+        format!(r#"{{ "id": "test", "name": "Test", "width": 800, "height": 800, "obstacles": [{}] }}"#, obstacle)
+    }
+
+    #[test]
+    fn shipped_maps_are_valid() {
+        let (maps, skipped) = MapDefinition::load_dir(Path::new("maps")).unwrap();
+        assert_eq!(skipped, Vec::<String>::new());
+        assert_eq!(maps.len(), 3);
+    }
+
+    #[test]
+    fn second_map_with_the_same_id_is_skipped() {
+        let directory = std::env::temp_dir().join(format!("maps-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("a.json"), r#"{ "id": "twin", "name": "First", "width": 800, "height": 800 }"#).unwrap();
+        fs::write(directory.join("b.json"), r#"{ "id": "twin", "name": "Second", "width": 800, "height": 800 }"#).unwrap();
+        let (maps, skipped) = MapDefinition::load_dir(&directory).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(maps.iter().map(MapDefinition::name).collect::<Vec<_>>(), vec!["First"]);
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("b.json"));
+    }
+
+    #[test]
+    fn convex_polygons_are_accepted_in_either_winding() {
+        assert!(parse(&with_obstacle(r#"{ "shape": "polygon", "vertices": [[100, 100], [200, 100], [200, 150], [200, 200], [100, 200]], "height": 50 }"#)).is_ok());
+        assert!(parse(&with_obstacle(r#"{ "shape": "polygon", "vertices": [[100, 100], [100, 200], [200, 200]], "height": 50 }"#)).is_ok());
+    }
+
+    #[test]
+    fn non_convex_polygons_are_rejected() {
+        let notched = r#"{ "shape": "polygon", "vertices": [[100, 100], [300, 100], [200, 150], [300, 200], [100, 200]], "height": 50 }"#;
+        assert!(parse(&with_obstacle(notched)).is_err());
+        let star = r#"{ "shape": "polygon", "vertices": [[400, 100], [480, 350], [250, 200], [550, 200], [320, 350]], "height": 50 }"#;
+        assert!(parse(&with_obstacle(star)).is_err());
+        let terrain = r#"{ "id": "test", "name": "Test", "width": 800, "height": 800,
+            "terrain": [{ "shape": "polygon", "surface": "mud", "vertices": [[100, 100], [300, 100], [200, 150], [300, 200], [100, 200]] }] }"#;
+        assert!(parse(terrain).is_err());
+    }
+
+    #[test]
+    fn generated_arena_id_is_reserved() {
+        assert!(parse(r#"{ "id": "generated", "name": "Test", "width": 800, "height": 800 }"#).is_err());
+    }
+}
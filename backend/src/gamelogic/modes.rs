@@ -0,0 +1,226 @@
+use super::arena::Arena;
+use super::scoring::ScoringRules;
+use super::waves::WaveSchedule;
+use super::{ControllerEvent, MatchSettings, Player};
+use crate::{GameModeType, ServerGameFrameResponse, ServerLobbyResponse, Team};
+use std::collections::hash_map::HashMap;
+use battle_royale::BattleRoyale;
+use capture_the_flag::CaptureTheFlag;
+use elimination::Elimination;
+use king_of_the_hill::KingOfTheHill;
+use practice::Practice;
+use survival::Survival;
+use team_deathmatch::TeamDeathmatch;
+pub(super) use capture_the_flag::FLAG_CARRIER_SPEED;
+
+mod battle_royale;
+mod capture_the_flag;
+mod elimination;
+mod king_of_the_hill;
+mod practice;
+mod survival;
+mod team_deathmatch;
+
+/// Kills a deathmatch is played to. Counted apart from the score, so assists and bounties
+/// can't bring the end of the match forward.
+const DEATHMATCH_KILL_LIMIT: i32 = 5;
+/// Ticks a destroyed tank stays dead before it starts respawning.
+const RESPAWN_DELAY: i32 = 120;
+
+/// How a match ended.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(super) enum MatchResult {
+    Winner(i32),
+    WinningTeam(Team),
+    /// Over without anyone winning, like a survival match once every player is down.
+    NoWinner,
+    /// Tied for the lead, these players share it. Empty when two teams are tied.
+    Draw(Vec<i32>)
+}
+
+/// What the controller should do after a game mode's tick.
+#[derive(Debug, Default, Clone)]
+pub(super) struct ModeTick {
+    pub(super) score_changed: bool,
+    /// Bring every tank back at a fresh spawn and clear the arena of projectiles.
+    pub(super) new_round: bool,
+    /// Tanks the mode destroyed itself. They are handled like any other kill, with nobody credited.
+    pub(super) kills: Vec<i32>
+}
+
+/// The rules of a match: what a kill is worth, whether destroyed tanks come back and when
+/// the match is over. A new one is made for every match, so it can keep state of its own.
+pub(super) trait GameMode: Send {
+    fn mode_type(&self) -> GameModeType;
+    /// Fewest players in game for the match to go on.
+    fn min_players(&self) -> usize {
+        2
+    }
+    /// Whether a player can start the match on their own, without waiting for everyone in the
+    /// lobby to be ready.
+    fn is_solo(&self) -> bool {
+        false
+    }
+    /// Whether players are split into teams before the match starts.
+    fn has_teams(&self) -> bool {
+        false
+    }
+    fn team_scores(&self) -> Vec<(Team, i32)> {
+        Vec::new()
+    }
+    /// The team every player is put on in a mode that doesn't split them up, so co-op modes
+    /// can keep them from hurting each other.
+    fn players_team(&self) -> Option<Team> {
+        None
+    }
+    /// Whether every shell reports where it came down, how far it flew and what it was loaded with.
+    fn shows_hit_markers(&self) -> bool {
+        false
+    }
+    /// Whether the match can run out of time and be played on in sudden death. Modes that
+    /// come to an end by themselves don't.
+    fn has_clock(&self) -> bool {
+        true
+    }
+    /// Called once the arena for the match is chosen.
+    fn start(&mut self, _arena: &Arena) {}
+    /// `killer_id` is the victim's own id when a tank destroys itself. Returns true if any score changed.
+    fn on_kill(&mut self, killer_id: i32, victim_id: i32, players: &mut HashMap<i32, Player>) -> bool;
+    /// Ticks until a destroyed tank starts respawning, `None` leaves it spectating until the
+    /// mode starts a new round.
+    fn respawn_delay(&self, victim: &Player) -> Option<i32>;
+    /// Runs every tick of the match once the tanks have moved and the kills are handled.
+    fn tick(&mut self, _players: &mut HashMap<i32, Player>, _arena: &Arena, _events: &mut Vec<ControllerEvent>) -> ModeTick {
+        ModeTick::default()
+    }
+    fn result(&self, players: &HashMap<i32, Player>) -> Option<MatchResult>;
+    /// Whoever leads right now, or everyone tied for the lead. Decides a match when its time is
+    /// up and whether sudden death has been won.
+    fn standings(&self, players: &HashMap<i32, Player>) -> MatchResult {
+        player_standings(players)
+    }
+    /// Fills in the lobby fields that only make sense for this mode.
+    fn write_lobby(&self, _lobby_response: &mut ServerLobbyResponse) {}
+    /// Adds whatever the mode has on the arena to a frame.
+    fn write_frame(&self, _frame_response: &mut ServerGameFrameResponse) {}
+}
+
+/// `waves` is only used by survival matches.
+pub(super) fn create(settings: &MatchSettings, tick_rate: f64, waves: &WaveSchedule) -> Box<dyn GameMode> {
+    match settings.game_mode {
+        GameModeType::team_deathmatch => Box::new(TeamDeathmatch::new(settings)),
+        GameModeType::elimination => Box::new(Elimination::new()),
+        GameModeType::capture_the_flag => Box::new(CaptureTheFlag::new()),
+        GameModeType::king_of_the_hill => Box::new(KingOfTheHill::new(settings, tick_rate)),
+        GameModeType::battle_royale => Box::new(BattleRoyale::new(settings, tick_rate)),
+        GameModeType::survival => Box::new(Survival::new(settings, tick_rate, waves)),
+        GameModeType::practice => Box::new(Practice::new(settings)),
+        GameModeType::deathmatch | GameModeType::empty_18 => Box::new(Deathmatch { kill_limit: DEATHMATCH_KILL_LIMIT, scoring: settings.scoring })
+    }
+}
+
+/// The player with the top score, or all of them if it is shared. Bots never win.
+fn player_standings(players: &HashMap<i32, Player>) -> MatchResult {
+    let top_score = players.values().filter(|player| !player.is_bot).map(Player::get_score).max();
+    let mut leaders: Vec<i32> = players.values()
+        .filter(|player| !player.is_bot && Some(player.get_score()) == top_score)
+        .map(|player| player.id)
+        .collect();
+    leaders.sort();
+    match leaders[..] {
+        [leader] => MatchResult::Winner(leader),
+        _ => MatchResult::Draw(leaders)
+    }
+}
+
+/// The team with the top score, or a draw if they are level.
+fn team_standings(scores: &[(Team, i32)]) -> MatchResult {
+    let top_score = scores.iter().map(|(_, score)| *score).max();
+    match scores.iter().filter(|(_, score)| Some(*score) == top_score).collect::<Vec<_>>()[..] {
+        [(team, _)] => MatchResult::WinningTeam(*team),
+        _ => MatchResult::Draw(Vec::new())
+    }
+}
+
+/// Every tank for itself, kills scored by the match's `ScoringRules` and the first to the kill limit wins.
+pub(super) struct Deathmatch {
+    kill_limit: i32,
+    scoring: ScoringRules
+}
+
+impl GameMode for Deathmatch {
+    fn mode_type(&self) -> GameModeType {
+        GameModeType::deathmatch
+    }
+    fn on_kill(&mut self, killer_id: i32, victim_id: i32, players: &mut HashMap<i32, Player>) -> bool {
+        self.scoring.score_kill(killer_id, victim_id, players)
+    }
+    fn respawn_delay(&self, _victim: &Player) -> Option<i32> {
+        Some(RESPAWN_DELAY)
+    }
+    /// Two players reaching the limit on the same tick are tied rather than settled by whoever is found first.
+    fn result(&self, players: &HashMap<i32, Player>) -> Option<MatchResult> {
+        let mut finishers: Vec<i32> = players.values()
+            .filter(|player| player.kills >= self.kill_limit)
+            .map(|player| player.id)
+            .collect();
+        finishers.sort();
+        match finishers[..] {
+            [] => None,
+            [winner] => Some(MatchResult::Winner(winner)),
+            _ => Some(MatchResult::Draw(finishers))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamelogic::arena::Arena;
+    use crate::gamelogic::ControllerPoint;
+    use crate::{AbilityType, PlayerInGameStatus, TankClass};
+
+    fn deathmatch(ids: &[i32]) -> (Box<dyn GameMode>, HashMap<i32, Player>) {
+        let players = ids.iter().map(|id| (*id, Player::new(*id, ControllerPoint { x: 0.0, y: 0.0 }, 0.0, AbilityType::empty_15, TankClass::medium, None))).collect();
+        (create(&MatchSettings::default(), 60.0, &WaveSchedule::default()), players)
+    }
+
+    #[test]
+    fn deathmatch_ends_on_kills_not_points() {
+        let (mode, mut players) = deathmatch(&[1, 2]);
+        let leader = players.get_mut(&1).unwrap();
+        leader.kills = DEATHMATCH_KILL_LIMIT - 1;
+        leader.add_score(100);
+        assert_eq!(mode.result(&players), None);
+
+        players.get_mut(&2).unwrap().kills = DEATHMATCH_KILL_LIMIT;
+        assert_eq!(mode.result(&players), Some(MatchResult::Winner(2)));
+    }
+
+    #[test]
+    fn deathmatch_reached_by_two_players_at_once_is_a_draw() {
+        let (mode, mut players) = deathmatch(&[1, 2, 3]);
+        players.get_mut(&3).unwrap().kills = DEATHMATCH_KILL_LIMIT;
+        players.get_mut(&1).unwrap().kills = DEATHMATCH_KILL_LIMIT;
+        assert_eq!(mode.result(&players), Some(MatchResult::Draw(vec![1, 3])));
+    }
+
+    #[test]
+    fn survival_credits_a_cleared_wave_only_to_the_survivors() {
+        let settings = MatchSettings { game_mode: GameModeType::survival, ..MatchSettings::default() };
+        let mut mode = create(&settings, 60.0, &WaveSchedule::default());
+        let (_, mut players) = deathmatch(&[1, 2]);
+        let arena = Arena::default_layout(1200, 800);
+        let mut events = Vec::new();
+        while !players.values().any(|player| player.is_bot) {
+            mode.tick(&mut players, &arena, &mut events);
+        }
+
+        players.values_mut()
+            .filter(|player| player.is_bot || player.id == 2)
+            .for_each(|player| player.player_in_game_status = PlayerInGameStatus::spectating);
+        mode.tick(&mut players, &arena, &mut events);
+        assert_eq!(players[&1].score, 1);
+        assert_eq!(players[&2].score, 0);
+    }
+}
@@ -0,0 +1,189 @@
+use super::{GameMode, MatchResult, ModeTick};
+use crate::gamelogic::arena::Arena;
+use crate::gamelogic::scoring::ScoringRules;
+use crate::gamelogic::{ControllerEvent, ControllerPoint, MatchSettings, Player};
+use crate::{GameModeType, PlayerInGameStatus, SafeZoneResponse, ServerGameFrameResponse};
+use rand::{thread_rng, Rng};
+use std::collections::hash_map::HashMap;
+
+/// One step of the zone closing in: it holds still for `wait` seconds, then takes `shrink`
+/// seconds to close in on the next zone.
+struct Phase {
+    wait: f64,
+    shrink: f64,
+    /// Radius of the next zone as a fraction of the one before it.
+    scale: f32,
+    /// Damage a second to tanks outside the zone, before armor.
+    damage: f32
+}
+
+/// The last phase closes the zone completely, so a match always ends.
+const PHASES: [Phase; 5] = [
+    Phase { wait: 30.0, shrink: 20.0, scale: 0.65, damage: 5.0 },
+    Phase { wait: 25.0, shrink: 15.0, scale: 0.6, damage: 8.0 },
+    Phase { wait: 20.0, shrink: 15.0, scale: 0.5, damage: 12.0 },
+    Phase { wait: 15.0, shrink: 10.0, scale: 0.4, damage: 18.0 },
+    Phase { wait: 10.0, shrink: 10.0, scale: 0.0, damage: 25.0 }
+];
+
+#[derive(Debug, Clone, Copy)]
+struct Circle {
+    center: ControllerPoint,
+    radius: f32
+}
+
+impl Circle {
+    fn contains(&self, point: ControllerPoint) -> bool {
+        (point.x - self.center.x).powi(2) + (point.y - self.center.y).powi(2) <= self.radius.powi(2)
+    }
+    fn lerp(&self, to: Circle, t: f32) -> Circle {
+        Circle {
+            center: ControllerPoint {
+                x: self.center.x + (to.center.x - self.center.x) * t,
+                y: self.center.y + (to.center.y - self.center.y) * t
+            },
+            radius: self.radius + (to.radius - self.radius) * t
+        }
+    }
+    /// A random circle `scale` times the size that lies inside this one, kept on the arena.
+    fn shrunk(&self, scale: f32, width: f32, height: f32) -> Circle {
+        let mut rng = thread_rng();
+        let radius = self.radius * scale;
+        let angle = rng.gen::<f32>() * std::f32::consts::TAU;
+        let distance = rng.gen::<f32>() * (self.radius - radius);
+        Circle {
+            center: ControllerPoint {
+                x: (self.center.x + distance * angle.cos()).clamp(0.0, width),
+                y: (self.center.y + distance * angle.sin()).clamp(0.0, height)
+            },
+            radius
+        }
+    }
+}
+
+/// Every tank for itself with no respawns, in a safe zone that shrinks in phases. Tanks outside
+/// it take damage every second. Kills are scored by the match's `ScoringRules` and the last
+/// tank left wins.
+pub(super) struct BattleRoyale {
+    scoring: ScoringRules,
+    tick_rate: f64,
+    width: f32,
+    height: f32,
+    phase: usize,
+    /// Ticks into the current phase.
+    phase_ticks: i32,
+    zone: Circle,
+    /// The zone the current phase shrinks from.
+    from: Circle,
+    /// The zone the current phase shrinks to.
+    next: Circle,
+    damage_timer: i32
+}
+
+impl BattleRoyale {
+    pub(super) fn new(settings: &MatchSettings, tick_rate: f64) -> Self {
+        let everywhere = Circle { center: ControllerPoint { x: 0.0, y: 0.0 }, radius: f32::MAX };
+        BattleRoyale {
+            scoring: settings.scoring,
+            tick_rate,
+            width: 0.0,
+            height: 0.0,
+            phase: 0,
+            phase_ticks: 0,
+            zone: everywhere,
+            from: everywhere,
+            next: everywhere,
+            damage_timer: 0
+        }
+    }
+    fn ticks(&self, seconds: f64) -> i32 {
+        ((seconds * self.tick_rate) as i32).max(1)
+    }
+    /// Ticks until the zone starts closing in again, 0 while it is.
+    fn shrink_ticks(&self) -> i32 {
+        PHASES.get(self.phase).map_or(0, |phase| (self.ticks(phase.wait) - self.phase_ticks).max(0))
+    }
+    fn close_in(&mut self) {
+        let Some(phase) = PHASES.get(self.phase) else {
+            return;
+        };
+        self.phase_ticks += 1;
+        let shrinking_for = self.phase_ticks - self.ticks(phase.wait);
+        if shrinking_for <= 0 {
+            return;
+        }
+        let t = (shrinking_for as f32 / self.ticks(phase.shrink) as f32).min(1.0);
+        self.zone = self.from.lerp(self.next, t);
+        if t < 1.0 {
+            return;
+        }
+        self.phase += 1;
+        self.phase_ticks = 0;
+        self.from = self.next;
+        if let Some(next_phase) = PHASES.get(self.phase) {
+            self.next = self.from.shrunk(next_phase.scale, self.width, self.height);
+        }
+    }
+}
+
+impl GameMode for BattleRoyale {
+    fn mode_type(&self) -> GameModeType {
+        GameModeType::battle_royale
+    }
+    fn start(&mut self, arena: &Arena) {
+        self.width = arena.width as f32;
+        self.height = arena.height as f32;
+        let whole_arena = Circle {
+            center: ControllerPoint { x: self.width / 2.0, y: self.height / 2.0 },
+            radius: (self.width.powi(2) + self.height.powi(2)).sqrt() / 2.0
+        };
+        self.zone = whole_arena;
+        self.from = whole_arena;
+        self.next = whole_arena.shrunk(PHASES[0].scale, self.width, self.height);
+        self.damage_timer = self.ticks(1.0);
+    }
+    fn on_kill(&mut self, killer_id: i32, victim_id: i32, players: &mut HashMap<i32, Player>) -> bool {
+        self.scoring.score_kill(killer_id, victim_id, players)
+    }
+    fn has_clock(&self) -> bool {
+        false
+    }
+    fn respawn_delay(&self, _victim: &Player) -> Option<i32> {
+        None
+    }
+    fn tick(&mut self, players: &mut HashMap<i32, Player>, _arena: &Arena, _events: &mut Vec<ControllerEvent>) -> ModeTick {
+        self.close_in();
+
+        self.damage_timer -= 1;
+        if self.damage_timer > 0 {
+            return ModeTick::default();
+        }
+        self.damage_timer = self.ticks(1.0);
+        let damage = PHASES[self.phase.min(PHASES.len() - 1)].damage;
+        let kills = players.values_mut()
+            .filter(|player| player.is_alive() && !self.zone.contains(player.center()))
+            .filter_map(|player| player.take_damage(damage).then_some(player.id))
+            .collect();
+        ModeTick { kills, ..ModeTick::default() }
+    }
+    /// The last tank left. If the last few go out together, whoever of all of them has the top score.
+    fn result(&self, players: &HashMap<i32, Player>) -> Option<MatchResult> {
+        let mut survivors = players.values().filter(|player| player.player_in_game_status != PlayerInGameStatus::spectating);
+        match (survivors.next(), survivors.next()) {
+            (Some(survivor), None) => Some(MatchResult::Winner(survivor.id)),
+            (None, _) => Some(self.standings(players)),
+            _ => None
+        }
+    }
+    fn write_frame(&self, frame_response: &mut ServerGameFrameResponse) {
+        let mut safe_zone_response = SafeZoneResponse::new();
+        safe_zone_response.set_center(self.zone.center.to_buffer_point());
+        safe_zone_response.set_radius(self.zone.radius.min(i32::MAX as f32) as i32);
+        safe_zone_response.set_next_center(self.next.center.to_buffer_point());
+        safe_zone_response.set_next_radius(self.next.radius.min(i32::MAX as f32) as i32);
+        safe_zone_response.set_shrink_ticks(self.shrink_ticks());
+        safe_zone_response.set_shrinking(self.phase < PHASES.len() && self.shrink_ticks() == 0);
+        safe_zone_response.set_phase(self.phase as i32 + 1);
+        frame_response.set_safe_zone(safe_zone_response);
+    }
+}
@@ -0,0 +1,182 @@
+use super::{team_standings, GameMode, MatchResult, ModeTick, RESPAWN_DELAY};
+use crate::gamelogic::arena::Arena;
+use crate::gamelogic::{ControllerEvent, ControllerPoint, Player};
+use crate::{FlagResponse, GameModeType, ServerGameFrameResponse, Team};
+use protobuf::RepeatedField;
+use std::collections::hash_map::HashMap;
+
+/// Multiplies the acceleration and top speed of a tank carrying a flag.
+pub(in crate::gamelogic) const FLAG_CARRIER_SPEED: f32 = 0.7;
+const CAPTURES_TO_WIN: i32 = 3;
+const FLAG_RADIUS: f32 = 20.0;
+/// Ticks a dropped flag lies where it fell before it goes back to its base on its own.
+const RETURN_DELAY: i32 = 900;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum FlagState {
+    AtBase,
+    /// Carried by the player with this id.
+    Carried(i32),
+    /// Ticks left until it returns to its base.
+    Dropped(i32)
+}
+
+#[derive(Debug)]
+struct Flag {
+    team: Team,
+    base: ControllerPoint,
+    position: ControllerPoint,
+    state: FlagState
+}
+
+impl Flag {
+    fn new(team: Team, base: ControllerPoint) -> Self {
+        Flag { team, base, position: base, state: FlagState::AtBase }
+    }
+    fn is_touching(&self, player: &Player) -> bool {
+        let closest_x = self.position.x.clamp(player.position.x, player.position.x + player.stats.size);
+        let closest_y = self.position.y.clamp(player.position.y, player.position.y + player.stats.size);
+        (self.position.x - closest_x).powi(2) + (self.position.y - closest_y).powi(2) <= FLAG_RADIUS.powi(2)
+    }
+    /// Whether `player` can do anything with the flag by touching it: enemies take it unless
+    /// they already carry one, teammates return it once it's off its base.
+    fn can_be_touched_by(&self, player: &Player) -> bool {
+        let Some(team) = player.team else {
+            return false;
+        };
+        let interested = if team == self.team { self.state != FlagState::AtBase } else { player.carrying_flag.is_none() };
+        interested && player.is_alive() && self.is_touching(player)
+    }
+    fn return_to_base(&mut self) {
+        self.position = self.base;
+        self.state = FlagState::AtBase;
+    }
+    fn to_buffer_flag(&self) -> FlagResponse {
+        let mut flag_response = FlagResponse::new();
+        flag_response.set_team(self.team);
+        flag_response.set_position(self.position.to_buffer_point());
+        flag_response.set_at_base(self.state == FlagState::AtBase);
+        match self.state {
+            FlagState::Carried(carrier_id) => flag_response.set_carrier_id(carrier_id),
+            FlagState::Dropped(ticks_left) => flag_response.set_return_ticks(ticks_left),
+            FlagState::AtBase => ()
+        }
+        flag_response
+    }
+}
+
+/// Red against blue, each with a flag at its base. Touching the enemy flag picks it up and
+/// driving it to your own base while your flag is there scores. Carriers are slowed down
+/// and drop the flag when destroyed. A player's score is the flags they have captured.
+pub(super) struct CaptureTheFlag {
+    captures_to_win: i32,
+    flags: Vec<Flag>,
+    scores: HashMap<Team, i32>
+}
+
+impl CaptureTheFlag {
+    pub(super) fn new() -> Self {
+        CaptureTheFlag {
+            captures_to_win: CAPTURES_TO_WIN,
+            flags: Vec::new(),
+            scores: HashMap::from([(Team::red, 0), (Team::blue, 0)])
+        }
+    }
+    /// Moves carried flags along with their carriers and hands them over to whoever touches them.
+    fn move_flags(&mut self, players: &mut HashMap<i32, Player>, events: &mut Vec<ControllerEvent>) {
+        for flag in self.flags.iter_mut() {
+            match flag.state {
+                FlagState::Carried(carrier_id) => {
+                    match players.get_mut(&carrier_id) {
+                        Some(carrier) if carrier.is_alive() => flag.position = carrier.center(),
+                        carrier => {
+                            // Destroyed, or left the match with it.
+                            if let Some(carrier) = carrier {
+                                carrier.carrying_flag = None;
+                                flag.position = carrier.center();
+                            }
+                            flag.state = FlagState::Dropped(RETURN_DELAY);
+                            events.push(ControllerEvent::FlagDropped { player_id: carrier_id, team: flag.team, position: flag.position });
+                        }
+                    }
+                    continue;
+                },
+                FlagState::Dropped(ticks_left) if ticks_left <= 1 => {
+                    flag.return_to_base();
+                    events.push(ControllerEvent::FlagReturned { player_id: 0, team: flag.team, position: flag.position });
+                    continue;
+                },
+                FlagState::Dropped(ticks_left) => flag.state = FlagState::Dropped(ticks_left - 1),
+                FlagState::AtBase => ()
+            }
+
+            let Some(player) = players.values_mut().find(|player| flag.can_be_touched_by(player)) else {
+                continue;
+            };
+            if player.team == Some(flag.team) {
+                flag.return_to_base();
+                events.push(ControllerEvent::FlagReturned { player_id: player.id, team: flag.team, position: flag.position });
+            } else {
+                flag.state = FlagState::Carried(player.id);
+                player.carrying_flag = Some(flag.team);
+                events.push(ControllerEvent::FlagTaken { player_id: player.id, team: flag.team, position: flag.position });
+            }
+        }
+    }
+}
+
+impl GameMode for CaptureTheFlag {
+    fn mode_type(&self) -> GameModeType {
+        GameModeType::capture_the_flag
+    }
+    fn has_teams(&self) -> bool {
+        true
+    }
+    fn team_scores(&self) -> Vec<(Team, i32)> {
+        [Team::red, Team::blue].into_iter().map(|team| (team, self.scores[&team])).collect()
+    }
+    fn start(&mut self, arena: &Arena) {
+        self.flags = [Team::red, Team::blue].into_iter().map(|team| Flag::new(team, arena.base_of(team))).collect();
+    }
+    fn on_kill(&mut self, _killer_id: i32, _victim_id: i32, _players: &mut HashMap<i32, Player>) -> bool {
+        false
+    }
+    fn respawn_delay(&self, _victim: &Player) -> Option<i32> {
+        Some(RESPAWN_DELAY)
+    }
+    fn tick(&mut self, players: &mut HashMap<i32, Player>, _arena: &Arena, events: &mut Vec<ControllerEvent>) -> ModeTick {
+        self.move_flags(players, events);
+
+        let mut mode_tick = ModeTick::default();
+        for index in 0..self.flags.len() {
+            let FlagState::Carried(carrier_id) = self.flags[index].state else {
+                continue;
+            };
+            let Some(carrier) = players.get_mut(&carrier_id) else {
+                continue;
+            };
+            let is_home = self.flags.iter().any(|flag| Some(flag.team) == carrier.team && flag.state == FlagState::AtBase && flag.is_touching(carrier));
+            let Some(team) = carrier.team.filter(|_| is_home) else {
+                continue;
+            };
+            carrier.carrying_flag = None;
+            carrier.increment_score();
+            *self.scores.entry(team).or_insert(0) += 1;
+            events.push(ControllerEvent::FlagCaptured { player_id: carrier_id, team: self.flags[index].team, position: carrier.center() });
+            self.flags[index].return_to_base();
+            mode_tick.score_changed = true;
+        }
+        mode_tick
+    }
+    fn result(&self, players: &HashMap<i32, Player>) -> Option<MatchResult> {
+        self.scores.values()
+            .any(|score| *score >= self.captures_to_win)
+            .then(|| self.standings(players))
+    }
+    fn standings(&self, _players: &HashMap<i32, Player>) -> MatchResult {
+        team_standings(&self.team_scores())
+    }
+    fn write_frame(&self, frame_response: &mut ServerGameFrameResponse) {
+        frame_response.set_flags(RepeatedField::from_vec(self.flags.iter().map(Flag::to_buffer_flag).collect()));
+    }
+}
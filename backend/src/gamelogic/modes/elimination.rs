@@ -0,0 +1,75 @@
+use super::{GameMode, MatchResult, ModeTick};
+use crate::gamelogic::arena::Arena;
+use crate::gamelogic::{ControllerEvent, Player};
+use crate::{GameModeType, PlayerInGameStatus, ServerLobbyResponse};
+use std::collections::hash_map::HashMap;
+
+/// Round wins needed to take the match, so it is played best of five.
+const ROUNDS_TO_WIN: i32 = 3;
+/// Ticks between a round being decided and the next one starting.
+const INTERMISSION: i32 = 180;
+
+/// Last tank standing. Destroyed tanks spectate until the round is over and the last one still
+/// in it wins the round. A player's score is the rounds they have won.
+pub(super) struct Elimination {
+    rounds_to_win: i32,
+    round: i32,
+    /// Ticks left until the next round, 0 while a round is being played.
+    intermission: i32
+}
+
+impl Elimination {
+    pub(super) fn new() -> Self {
+        Elimination {
+            rounds_to_win: ROUNDS_TO_WIN,
+            round: 1,
+            intermission: 0
+        }
+    }
+}
+
+impl GameMode for Elimination {
+    fn mode_type(&self) -> GameModeType {
+        GameModeType::elimination
+    }
+    fn on_kill(&mut self, _killer_id: i32, _victim_id: i32, _players: &mut HashMap<i32, Player>) -> bool {
+        false
+    }
+    fn respawn_delay(&self, _victim: &Player) -> Option<i32> {
+        None
+    }
+    fn tick(&mut self, players: &mut HashMap<i32, Player>, _arena: &Arena, events: &mut Vec<ControllerEvent>) -> ModeTick {
+        if self.intermission > 0 {
+            self.intermission -= 1;
+            if self.intermission > 0 {
+                return ModeTick::default();
+            }
+            self.round += 1;
+            events.push(ControllerEvent::RoundStart { round: self.round });
+            return ModeTick { new_round: true, ..ModeTick::default() };
+        }
+
+        let mut survivors = players.values_mut().filter(|player| player.player_in_game_status != PlayerInGameStatus::spectating);
+        let (Some(survivor), None) = (survivors.next(), survivors.next()) else {
+            // Still a fight, or the last tanks took each other out and nobody gets the round.
+            if players.values().all(|player| player.player_in_game_status == PlayerInGameStatus::spectating) {
+                events.push(ControllerEvent::RoundEnd { winner_id: 0, round: self.round });
+                self.intermission = INTERMISSION;
+            }
+            return ModeTick::default();
+        };
+        survivor.increment_score();
+        events.push(ControllerEvent::RoundEnd { winner_id: survivor.id, round: self.round });
+        self.intermission = INTERMISSION;
+        ModeTick { score_changed: true, ..ModeTick::default() }
+    }
+    fn result(&self, players: &HashMap<i32, Player>) -> Option<MatchResult> {
+        players.values()
+            .any(|player| player.get_score() >= self.rounds_to_win)
+            .then(|| self.standings(players))
+    }
+    fn write_lobby(&self, lobby_response: &mut ServerLobbyResponse) {
+        lobby_response.set_round(self.round);
+        lobby_response.set_rounds_to_win(self.rounds_to_win);
+    }
+}
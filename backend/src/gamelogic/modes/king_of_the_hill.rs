@@ -0,0 +1,133 @@
+use super::{GameMode, MatchResult, ModeTick, RESPAWN_DELAY};
+use crate::gamelogic::arena::Arena;
+use crate::gamelogic::{ControllerEvent, ControllerPoint, MatchSettings, Player};
+use crate::{GameModeType, HillResponse, ServerGameFrameResponse};
+use std::collections::hash_map::HashMap;
+
+/// Points needed to win, a point for every second of holding the zone alone.
+const SCORE_LIMIT: i32 = 60;
+const ZONE_RADIUS: f32 = 80.0;
+/// Zones picked for arenas that have neither hills nor pickup points.
+const RANDOM_HILLS: usize = 3;
+
+/// Every tank for itself around a control zone. A tank alone in the zone earns a point every
+/// second, a contested zone earns nothing and the zone can move through the arena's hills.
+pub(super) struct KingOfTheHill {
+    score_limit: i32,
+    ticks_per_point: i32,
+    /// Ticks between the zone moving on, 0 keeps it in place.
+    rotation_interval: i32,
+    rotation_timer: i32,
+    hills: Vec<ControllerPoint>,
+    hill_index: usize,
+    owner_id: Option<i32>,
+    contested: bool,
+    /// Ticks the owner has held the zone towards its next point.
+    held: i32
+}
+
+impl KingOfTheHill {
+    pub(super) fn new(settings: &MatchSettings, tick_rate: f64) -> Self {
+        let rotation_interval = (settings.hill_rotation * tick_rate) as i32;
+        KingOfTheHill {
+            score_limit: SCORE_LIMIT,
+            ticks_per_point: (tick_rate.round() as i32).max(1),
+            rotation_interval,
+            rotation_timer: rotation_interval,
+            hills: Vec::new(),
+            hill_index: 0,
+            owner_id: None,
+            contested: false,
+            held: 0
+        }
+    }
+    /// `None` until the match has started.
+    fn position(&self) -> Option<ControllerPoint> {
+        self.hills.get(self.hill_index).copied()
+    }
+    fn covers(&self, point: ControllerPoint) -> bool {
+        self.position().is_some_and(|position| (point.x - position.x).powi(2) + (point.y - position.y).powi(2) <= ZONE_RADIUS.powi(2))
+    }
+    fn rotate(&mut self, events: &mut Vec<ControllerEvent>) {
+        self.rotation_timer = self.rotation_interval;
+        if self.hills.len() < 2 {
+            return;
+        }
+        self.hill_index = (self.hill_index + 1) % self.hills.len();
+        self.owner_id = None;
+        self.held = 0;
+        events.push(ControllerEvent::HillMoved { position: self.hills[self.hill_index] });
+    }
+}
+
+impl GameMode for KingOfTheHill {
+    fn mode_type(&self) -> GameModeType {
+        GameModeType::king_of_the_hill
+    }
+    /// The arena's hills, or its pickup points, or a few random free spots.
+    fn start(&mut self, arena: &Arena) {
+        self.hills = match (arena.hills.is_empty(), arena.pickup_points.is_empty()) {
+            (false, _) => arena.hills.clone(),
+            (true, false) => arena.pickup_points.clone(),
+            (true, true) => (0..RANDOM_HILLS).map(|_| {
+                let corner = arena.random_free_point(ZONE_RADIUS);
+                ControllerPoint { x: corner.x + ZONE_RADIUS / 2.0, y: corner.y + ZONE_RADIUS / 2.0 }
+            }).collect()
+        };
+    }
+    fn on_kill(&mut self, _killer_id: i32, _victim_id: i32, _players: &mut HashMap<i32, Player>) -> bool {
+        false
+    }
+    fn respawn_delay(&self, _victim: &Player) -> Option<i32> {
+        Some(RESPAWN_DELAY)
+    }
+    fn tick(&mut self, players: &mut HashMap<i32, Player>, _arena: &Arena, events: &mut Vec<ControllerEvent>) -> ModeTick {
+        if self.rotation_interval > 0 {
+            self.rotation_timer -= 1;
+            if self.rotation_timer <= 0 {
+                self.rotate(events);
+            }
+        }
+
+        let inside: Vec<i32> = players.values()
+            .filter(|player| player.is_alive() && self.covers(player.center()))
+            .map(|player| player.id)
+            .collect();
+        self.contested = inside.len() > 1;
+        let owner_id = if let [owner_id] = inside[..] { Some(owner_id) } else { None };
+        if owner_id != self.owner_id {
+            self.owner_id = owner_id;
+            self.held = 0;
+        }
+
+        let Some(owner) = owner_id.and_then(|owner_id| players.get_mut(&owner_id)) else {
+            return ModeTick::default();
+        };
+        self.held += 1;
+        if self.held < self.ticks_per_point {
+            return ModeTick::default();
+        }
+        self.held = 0;
+        owner.increment_score();
+        ModeTick { score_changed: true, ..ModeTick::default() }
+    }
+    fn result(&self, players: &HashMap<i32, Player>) -> Option<MatchResult> {
+        players.values()
+            .any(|player| player.get_score() >= self.score_limit)
+            .then(|| self.standings(players))
+    }
+    fn write_frame(&self, frame_response: &mut ServerGameFrameResponse) {
+        let Some(position) = self.position() else {
+            return;
+        };
+        let mut hill_response = HillResponse::new();
+        hill_response.set_position(position.to_buffer_point());
+        hill_response.set_radius(ZONE_RADIUS as i32);
+        hill_response.set_owner_id(self.owner_id.unwrap_or(0));
+        hill_response.set_contested(self.contested);
+        // Percent of the way to the owner's next point.
+        hill_response.set_progress(self.held * 100 / self.ticks_per_point);
+        hill_response.set_rotation_ticks(self.rotation_timer.max(0));
+        frame_response.set_hill(hill_response);
+    }
+}
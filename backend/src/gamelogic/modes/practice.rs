@@ -0,0 +1,87 @@
+use super::{GameMode, MatchResult, ModeTick, RESPAWN_DELAY};
+use crate::gamelogic::arena::Arena;
+use crate::gamelogic::bots::{self, Bot, BotKind};
+use crate::gamelogic::{ControllerEvent, MatchSettings, Player};
+use crate::{GameModeType, TankClass, Team};
+use std::collections::hash_map::HashMap;
+
+/// Team the players practice on, the dummies stand on the other side of the arena.
+const PLAYER_TEAM: Team = Team::red;
+const DUMMY_TEAM: Team = Team::blue;
+const STANDING_DUMMIES: usize = 3;
+const MOVING_DUMMIES: usize = 2;
+
+/// A shooting range that one player can start alone. Target dummies that never shoot back stand
+/// or drive up and down on the far side and come back when destroyed, every shell reports where
+/// it landed and the match goes on until the players leave. A player's score is the dummies
+/// they have destroyed.
+pub(super) struct Practice {
+    armor: f32,
+    muzzle_velocity: f32,
+    dummies: HashMap<i32, Bot>
+}
+
+impl Practice {
+    pub(super) fn new(settings: &MatchSettings) -> Self {
+        Practice {
+            armor: settings.armor,
+            muzzle_velocity: settings.muzzle_velocity,
+            dummies: HashMap::new()
+        }
+    }
+    fn set_up_dummies(&mut self, players: &mut HashMap<i32, Player>, arena: &Arena) {
+        let kinds = [BotKind::Dummy; STANDING_DUMMIES].into_iter().chain([BotKind::MovingDummy; MOVING_DUMMIES]);
+        for (index, kind) in kinds.enumerate() {
+            let id = -(index as i32) - 1;
+            let dummy = bots::spawn(id, TankClass::medium, DUMMY_TEAM, self.armor, players, arena);
+            players.insert(id, dummy);
+            self.dummies.insert(id, Bot::new(kind));
+        }
+    }
+}
+
+impl GameMode for Practice {
+    fn mode_type(&self) -> GameModeType {
+        GameModeType::practice
+    }
+    fn min_players(&self) -> usize {
+        1
+    }
+    fn is_solo(&self) -> bool {
+        true
+    }
+    fn has_clock(&self) -> bool {
+        false
+    }
+    fn players_team(&self) -> Option<Team> {
+        Some(PLAYER_TEAM)
+    }
+    fn shows_hit_markers(&self) -> bool {
+        true
+    }
+    fn on_kill(&mut self, killer_id: i32, victim_id: i32, players: &mut HashMap<i32, Player>) -> bool {
+        if !self.dummies.contains_key(&victim_id) {
+            return false;
+        }
+        match players.get_mut(&killer_id) {
+            Some(killer) => {
+                killer.increment_score();
+                true
+            },
+            None => false
+        }
+    }
+    fn respawn_delay(&self, _victim: &Player) -> Option<i32> {
+        Some(RESPAWN_DELAY)
+    }
+    fn tick(&mut self, players: &mut HashMap<i32, Player>, arena: &Arena, _events: &mut Vec<ControllerEvent>) -> ModeTick {
+        if self.dummies.is_empty() {
+            self.set_up_dummies(players, arena);
+        }
+        bots::drive_all(&mut self.dummies, players, self.muzzle_velocity);
+        ModeTick::default()
+    }
+    fn result(&self, _players: &HashMap<i32, Player>) -> Option<MatchResult> {
+        None
+    }
+}
@@ -0,0 +1,120 @@
+use super::{GameMode, MatchResult, ModeTick};
+use crate::gamelogic::arena::Arena;
+use crate::gamelogic::bots::{self, Bot};
+use crate::gamelogic::waves::WaveSchedule;
+use crate::gamelogic::{ControllerEvent, MatchSettings, Player};
+use crate::{GameModeType, PlayerInGameStatus, ServerLobbyResponse, Team};
+use std::collections::hash_map::HashMap;
+
+/// Team the players fight on, the waves come in on the other side of the arena.
+const PLAYER_TEAM: Team = Team::red;
+const ENEMY_TEAM: Team = Team::blue;
+/// Seconds from the match starting to the first wave rolling in.
+const FIRST_WAVE_DELAY: f64 = 3.0;
+
+/// The players together against waves of server controlled tanks and turrets from a
+/// `WaveSchedule`. Destroyed players spectate until the wave is cleared and the match is over
+/// once they are all down at the same time. A player's score is the waves survived.
+pub(super) struct Survival {
+    schedule: WaveSchedule,
+    tick_rate: f64,
+    armor: f32,
+    muzzle_velocity: f32,
+    /// The wave being fought or coming up next, 0 before the first.
+    wave: i32,
+    /// Ticks left until the next wave rolls in, 0 while one is being fought.
+    intermission: i32,
+    bots: HashMap<i32, Bot>,
+    /// Bots count down from -1, so their ids never clash with the clients'.
+    last_bot_id: i32
+}
+
+impl Survival {
+    pub(super) fn new(settings: &MatchSettings, tick_rate: f64, schedule: &WaveSchedule) -> Self {
+        Survival {
+            schedule: schedule.clone(),
+            tick_rate,
+            armor: settings.armor,
+            muzzle_velocity: settings.muzzle_velocity,
+            wave: 0,
+            intermission: ((FIRST_WAVE_DELAY * tick_rate) as i32).max(1),
+            bots: HashMap::new(),
+            last_bot_id: 0
+        }
+    }
+    /// Brings the next wave in on the enemy side, as far from the players as there is room for.
+    fn spawn_wave(&mut self, players: &mut HashMap<i32, Player>, arena: &Arena, events: &mut Vec<ControllerEvent>) {
+        self.wave += 1;
+        for (kind, tank_class) in self.schedule.enemies(self.wave) {
+            self.last_bot_id -= 1;
+            let bot = bots::spawn(self.last_bot_id, tank_class, ENEMY_TEAM, self.armor, players, arena);
+            players.insert(self.last_bot_id, bot);
+            self.bots.insert(self.last_bot_id, Bot::new(kind));
+        }
+        events.push(ControllerEvent::WaveStart { wave: self.wave });
+    }
+    fn is_cleared(&self, players: &HashMap<i32, Player>) -> bool {
+        self.bots.keys().all(|id| players.get(id).is_none_or(|bot| bot.player_in_game_status == PlayerInGameStatus::spectating))
+    }
+}
+
+impl GameMode for Survival {
+    fn mode_type(&self) -> GameModeType {
+        GameModeType::survival
+    }
+    fn min_players(&self) -> usize {
+        1
+    }
+    fn has_clock(&self) -> bool {
+        false
+    }
+    fn players_team(&self) -> Option<Team> {
+        Some(PLAYER_TEAM)
+    }
+    fn on_kill(&mut self, _killer_id: i32, _victim_id: i32, _players: &mut HashMap<i32, Player>) -> bool {
+        false
+    }
+    fn respawn_delay(&self, _victim: &Player) -> Option<i32> {
+        None
+    }
+    fn tick(&mut self, players: &mut HashMap<i32, Player>, arena: &Arena, events: &mut Vec<ControllerEvent>) -> ModeTick {
+        if self.intermission > 0 {
+            self.intermission -= 1;
+            if self.intermission == 0 {
+                self.spawn_wave(players, arena, events);
+            }
+            return ModeTick::default();
+        }
+
+        if !self.is_cleared(players) {
+            bots::drive_all(&mut self.bots, players, self.muzzle_velocity);
+            return ModeTick::default();
+        }
+
+        // Wrecks stay on the arena until the wave is over, so their shells still in the air
+        // know whose side they are on.
+        players.retain(|id, _| !self.bots.contains_key(id));
+        self.bots.clear();
+        for player in players.values_mut() {
+            if player.player_in_game_status == PlayerInGameStatus::spectating {
+                // Only the survivors are credited with the wave, the others are back in for the next one.
+                player.die();
+                player.cooldown = 1;
+            } else {
+                player.increment_score();
+            }
+        }
+        events.push(ControllerEvent::WaveCleared { wave: self.wave });
+        self.intermission = ((self.schedule.intermission() * self.tick_rate) as i32).max(1);
+        ModeTick { score_changed: true, ..ModeTick::default() }
+    }
+    fn result(&self, players: &HashMap<i32, Player>) -> Option<MatchResult> {
+        players.values()
+            .filter(|player| !player.is_bot)
+            .all(|player| player.player_in_game_status == PlayerInGameStatus::spectating)
+            .then_some(MatchResult::NoWinner)
+    }
+    fn write_lobby(&self, lobby_response: &mut ServerLobbyResponse) {
+        lobby_response.set_wave(self.wave);
+    }
+}
@@ -0,0 +1,62 @@
+use super::{team_standings, GameMode, MatchResult, RESPAWN_DELAY};
+use crate::gamelogic::scoring::ScoringRules;
+use crate::gamelogic::{MatchSettings, Player};
+use crate::{GameModeType, Team};
+use std::collections::hash_map::HashMap;
+
+/// Team score a team deathmatch is played to.
+const SCORE_LIMIT: i32 = 10;
+
+/// Red against blue. Every kill of an enemy tank scores a point for the killer's team and
+/// whatever the match's `ScoringRules` give for the killer, killing a teammate scores nothing
+/// and the first team to the score limit wins.
+pub(super) struct TeamDeathmatch {
+    score_limit: i32,
+    scoring: ScoringRules,
+    scores: HashMap<Team, i32>
+}
+
+impl TeamDeathmatch {
+    pub(super) fn new(settings: &MatchSettings) -> Self {
+        TeamDeathmatch {
+            score_limit: SCORE_LIMIT,
+            scoring: settings.scoring,
+            scores: HashMap::from([(Team::red, 0), (Team::blue, 0)])
+        }
+    }
+}
+
+impl GameMode for TeamDeathmatch {
+    fn mode_type(&self) -> GameModeType {
+        GameModeType::team_deathmatch
+    }
+    fn has_teams(&self) -> bool {
+        true
+    }
+    fn team_scores(&self) -> Vec<(Team, i32)> {
+        [Team::red, Team::blue].into_iter().map(|team| (team, self.scores[&team])).collect()
+    }
+    fn on_kill(&mut self, killer_id: i32, victim_id: i32, players: &mut HashMap<i32, Player>) -> bool {
+        if killer_id == victim_id {
+            return self.scoring.score_kill(killer_id, victim_id, players);
+        }
+        let victim_team = players.get(&victim_id).and_then(|victim| victim.team);
+        let Some(team) = players.get(&killer_id).and_then(|killer| killer.team).filter(|team| Some(*team) != victim_team) else {
+            return false;
+        };
+        *self.scores.entry(team).or_insert(0) += 1;
+        self.scoring.score_kill(killer_id, victim_id, players);
+        true
+    }
+    fn respawn_delay(&self, _victim: &Player) -> Option<i32> {
+        Some(RESPAWN_DELAY)
+    }
+    fn result(&self, players: &HashMap<i32, Player>) -> Option<MatchResult> {
+        self.scores.values()
+            .any(|score| *score >= self.score_limit)
+            .then(|| self.standings(players))
+    }
+    fn standings(&self, _players: &HashMap<i32, Player>) -> MatchResult {
+        team_standings(&self.team_scores())
+    }
+}
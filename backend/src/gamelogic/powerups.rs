@@ -0,0 +1,81 @@
+use super::arena::Arena;
+use super::ControllerPoint;
+use crate::{ActiveEffectResponse, PowerUpResponse, PowerUpType};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+const PICKUP_RADIUS: f32 = 15.0;
+pub(super) const MAX_PICKUPS: usize = 3;
+/// Seconds between pickups appearing while there is room for more.
+pub(super) const PICKUP_INTERVAL: f64 = 8.0;
+const POWER_UP_TYPES: [PowerUpType; 4] = [PowerUpType::speed_boost, PowerUpType::shield, PowerUpType::rapid_reload, PowerUpType::blast_radius];
+
+/// Multiplies a tank's acceleration and top speed.
+pub(super) const SPEED_BOOST: f32 = 1.5;
+/// How many times faster weapons reload.
+pub(super) const RAPID_RELOAD_RATE: i32 = 2;
+pub(super) const BLAST_RADIUS_SCALE: f32 = 1.5;
+
+/// A pickup lying on the arena, collected by driving into it.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PowerUp {
+    pub(super) power_up_type: PowerUpType,
+    pub(super) position: ControllerPoint
+}
+
+impl PowerUp {
+    /// A random power up on one of the arena's pickup points, or anywhere free if it has none.
+    /// Points already taken by `existing` pickups are skipped while there are others left.
+    pub(super) fn spawn(arena: &Arena, existing: &[ControllerPoint]) -> Self {
+        let mut rng = thread_rng();
+        let free_points: Vec<ControllerPoint> = arena.pickup_points.iter().copied().filter(|point| !existing.contains(point)).collect();
+        let position = match free_points.choose(&mut rng) {
+            Some(point) => *point,
+            None => {
+                let corner = arena.random_free_point(PICKUP_RADIUS * 2.0);
+                ControllerPoint { x: corner.x + PICKUP_RADIUS, y: corner.y + PICKUP_RADIUS }
+            }
+        };
+        PowerUp {
+            power_up_type: *POWER_UP_TYPES.choose(&mut rng).unwrap_or(&PowerUpType::speed_boost),
+            position
+        }
+    }
+    /// Seconds the effect lasts once collected. A shield also ends when it absorbs an explosion.
+    pub(super) fn duration(&self) -> f64 {
+        match self.power_up_type {
+            PowerUpType::shield => 15.0,
+            PowerUpType::blast_radius => 12.0,
+            _ => 10.0
+        }
+    }
+    pub(super) fn is_touching(&self, tank_position: ControllerPoint, tank_size: f32) -> bool {
+        let closest_x = self.position.x.clamp(tank_position.x, tank_position.x + tank_size);
+        let closest_y = self.position.y.clamp(tank_position.y, tank_position.y + tank_size);
+        (self.position.x - closest_x).powi(2) + (self.position.y - closest_y).powi(2) <= PICKUP_RADIUS.powi(2)
+    }
+    pub(super) fn to_buffer_power_up(self, id: i32) -> PowerUpResponse {
+        let mut power_up_response = PowerUpResponse::new();
+        power_up_response.set_id(id);
+        power_up_response.set_field_type(self.power_up_type);
+        power_up_response.set_position(self.position.to_buffer_point());
+        power_up_response
+    }
+}
+
+/// A collected power up still in effect.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ActiveEffect {
+    pub(super) power_up_type: PowerUpType,
+    /// Ticks left.
+    pub(super) remaining: i32
+}
+
+impl ActiveEffect {
+    pub(super) fn to_buffer_effect(self) -> ActiveEffectResponse {
+        let mut effect_response = ActiveEffectResponse::new();
+        effect_response.set_field_type(self.power_up_type);
+        effect_response.set_remaining_ticks(self.remaining);
+        effect_response
+    }
+}
@@ -0,0 +1,158 @@
+use super::Player;
+use crate::ScoringRulesInfo;
+use std::collections::hash_map::HashMap;
+
+/// What kills are worth in the modes scored by them, deathmatch, team deathmatch and battle royale.
+#[derive(Debug, Clone, Copy)]
+pub struct ScoringRules {
+    pub kill: i32,
+    /// For every other enemy that hurt the victim within `assist_window` before it went down.
+    pub assist: i32,
+    /// Taken off a player who destroys their own tank, like with a shell bounced back off a wall.
+    pub suicide_penalty: i32,
+    /// On top of `kill` for destroying whoever leads the match on their own.
+    pub bounty: i32,
+    /// Seconds damage to a tank still counts towards an assist.
+    pub assist_window: f64
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        ScoringRules {
+            kill: 2,
+            assist: 1,
+            suicide_penalty: 1,
+            bounty: 1,
+            assist_window: 5.0
+        }
+    }
+}
+
+impl ScoringRules {
+    pub(super) fn from_buffer(rules: &ScoringRulesInfo) -> Self {
+        let defaults = ScoringRules::default();
+        ScoringRules {
+            kill: if rules.has_kill() { rules.get_kill() } else { defaults.kill },
+            assist: if rules.has_assist() { rules.get_assist() } else { defaults.assist },
+            suicide_penalty: if rules.has_suicide_penalty() { rules.get_suicide_penalty() } else { defaults.suicide_penalty },
+            bounty: if rules.has_bounty() { rules.get_bounty() } else { defaults.bounty },
+            assist_window: if rules.has_assist_window() { rules.get_assist_window().max(0.0) } else { defaults.assist_window }
+        }
+    }
+    pub(super) fn to_buffer_scoring_rules(self) -> ScoringRulesInfo {
+        let mut rules = ScoringRulesInfo::new();
+        rules.set_kill(self.kill);
+        rules.set_assist(self.assist);
+        rules.set_suicide_penalty(self.suicide_penalty);
+        rules.set_bounty(self.bounty);
+        rules.set_assist_window(self.assist_window);
+        rules
+    }
+    /// Scores `victim_id` going down for the killer and everyone who helped, or penalizes the
+    /// victim if they did it themselves. Returns true if any score changed.
+    pub(super) fn score_kill(&self, killer_id: i32, victim_id: i32, players: &mut HashMap<i32, Player>) -> bool {
+        if killer_id == victim_id {
+            return match players.get_mut(&victim_id) {
+                Some(victim) if self.suicide_penalty != 0 => {
+                    victim.add_score(-self.suicide_penalty);
+                    true
+                },
+                _ => false
+            };
+        }
+        let Some(victim) = players.get(&victim_id) else {
+            return false;
+        };
+        let assist_ids = victim.assist_ids(killer_id);
+        let bounty = if victim.has_bounty { self.bounty } else { 0 };
+
+        let mut score_changed = false;
+        if let Some(killer) = players.get_mut(&killer_id) {
+            killer.add_score(self.kill + bounty);
+            score_changed = true;
+        }
+        for id in assist_ids.into_iter().filter(|_| self.assist != 0) {
+            if let Some(assistant) = players.get_mut(&id) {
+                assistant.add_score(self.assist);
+                score_changed = true;
+            }
+        }
+        score_changed
+    }
+}
+
+/// Whoever leads the match on their own. Bots are never in the lead, so there is no bounty on them.
+pub(super) fn sole_leader(players: &HashMap<i32, Player>) -> Option<i32> {
+    let leader = players.values().filter(|player| !player.is_bot).max_by_key(|player| player.get_score())?;
+    players.values()
+        .all(|other| other.id == leader.id || other.is_bot || other.get_score() < leader.get_score())
+        .then_some(leader.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamelogic::ControllerPoint;
+    use crate::{AbilityType, TankClass};
+
+    fn players(ids: &[i32]) -> HashMap<i32, Player> {
+        ids.iter().map(|id| (*id, Player::new(*id, ControllerPoint { x: 0.0, y: 0.0 }, 0.0, AbilityType::empty_15, TankClass::medium, None))).collect()
+    }
+
+    fn scores(players: &HashMap<i32, Player>) -> Vec<(i32, i32)> {
+        let mut scores: Vec<(i32, i32)> = players.values().map(|player| (player.id, player.get_score())).collect();
+        scores.sort();
+        scores
+    }
+
+    #[test]
+    fn suicide_costs_the_penalty() {
+        let mut players = players(&[1, 2]);
+        assert!(ScoringRules::default().score_kill(1, 1, &mut players));
+        assert_eq!(scores(&players), vec![(1, -1), (2, 0)]);
+
+        let rules = ScoringRules { suicide_penalty: 0, ..ScoringRules::default() };
+        assert!(!rules.score_kill(1, 1, &mut players));
+    }
+
+    #[test]
+    fn recent_attackers_other_than_the_killer_get_assists() {
+        let mut players = players(&[1, 2, 3, 4]);
+        let victim = players.get_mut(&2).unwrap();
+        victim.recent_attackers.insert(1, 10);
+        victim.recent_attackers.insert(3, 10);
+
+        assert!(ScoringRules::default().score_kill(1, 2, &mut players));
+        assert_eq!(scores(&players), vec![(1, 2), (2, 0), (3, 1), (4, 0)]);
+    }
+
+    #[test]
+    fn bounty_is_only_paid_for_a_victim_holding_it() {
+        let rules = ScoringRules { bounty: 3, ..ScoringRules::default() };
+        let mut players = players(&[1, 2]);
+        rules.score_kill(1, 2, &mut players);
+        assert_eq!(players[&1].get_score(), 2);
+
+        players.get_mut(&2).unwrap().has_bounty = true;
+        rules.score_kill(1, 2, &mut players);
+        assert_eq!(players[&1].get_score(), 2 + 2 + 3);
+    }
+
+    #[test]
+    fn sole_leader_needs_a_lead_over_every_other_player() {
+        let mut players = players(&[1, 2, 3]);
+        assert_eq!(sole_leader(&players), None);
+
+        players.get_mut(&1).unwrap().add_score(2);
+        players.get_mut(&2).unwrap().add_score(2);
+        assert_eq!(sole_leader(&players), None);
+
+        players.get_mut(&2).unwrap().add_score(1);
+        assert_eq!(sole_leader(&players), Some(2));
+
+        let bot = players.get_mut(&3).unwrap();
+        bot.is_bot = true;
+        bot.add_score(10);
+        assert_eq!(sole_leader(&players), Some(2));
+    }
+}
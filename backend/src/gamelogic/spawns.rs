@@ -0,0 +1,179 @@
+use super::arena::Arena;
+use super::ControllerPoint;
+use crate::Team;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+
+/// Random free points scored on arenas without spawn points of their own.
+const RANDOM_CANDIDATES: usize = 24;
+/// How many of the best candidates `SpawnStrategy::SafeRandom` picks between.
+const SAFE_RANDOM_PICKS: usize = 3;
+
+/// How a tank's spawn position is picked among the candidates that don't overlap anything.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SpawnStrategy {
+    /// Any candidate.
+    Random,
+    /// The candidate farthest from every threat.
+    Safest,
+    /// One of the few safest candidates, so spawns can't be camped.
+    SafeRandom
+}
+
+/// Something a tank shouldn't spawn next to.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Threat {
+    pub(super) position: ControllerPoint,
+    /// How far the threat reaches from its position, a tank whose hull comes closer overlaps it.
+    pub(super) clearance: f32,
+    /// Distances to the threat are divided by this, the player who just killed you counts double.
+    pub(super) danger: f32,
+    /// A teammate is only kept clear of, spawning next to one is as good as anywhere else.
+    pub(super) friendly: bool
+}
+
+impl Threat {
+    pub(super) fn tank(center: ControllerPoint, size: f32, danger: f32) -> Self {
+        Threat { position: center, clearance: half_diagonal(size), danger, friendly: false }
+    }
+    pub(super) fn teammate(center: ControllerPoint, size: f32) -> Self {
+        Threat { position: center, clearance: half_diagonal(size), danger: 1.0, friendly: true }
+    }
+    pub(super) fn blast(center: ControllerPoint, radius: f32) -> Self {
+        Threat { position: center, clearance: radius, danger: 1.0, friendly: false }
+    }
+    fn margin(&self, center: ControllerPoint, size: f32) -> f32 {
+        let distance = ((center.x - self.position.x).powi(2) + (center.y - self.position.y).powi(2)).sqrt();
+        let margin = (distance - self.clearance - half_diagonal(size)) / self.danger;
+        if self.friendly && margin > 0.0 { f32::MAX } else { margin }
+    }
+}
+
+/// Center to corner of a tank, so boxes are never closer than this without overlapping.
+fn half_diagonal(size: f32) -> f32 {
+    size * std::f32::consts::FRAC_1_SQRT_2
+}
+
+/// Red spawns on the left half of the arena, blue on the right.
+fn is_on_side(team: Team, arena: &Arena, center: ControllerPoint) -> bool {
+    let middle = arena.width as f32 / 2.0;
+    match team {
+        Team::blue => center.x >= middle,
+        _ => center.x < middle
+    }
+}
+
+/// Top left corner for a tank of `size`, picked with `strategy` among the arena's spawn points. Random
+/// free points are added on an arena without any, or when every spawn point overlaps a threat.
+/// Candidates inside an obstacle or overlapping a threat are never picked unless nothing else is
+/// left. A tank on a `team` only spawns on its team's side while there is a clear candidate there.
+pub(super) fn choose_spawn(strategy: SpawnStrategy, arena: &Arena, threats: &[Threat], size: f32, team: Option<Team>) -> ControllerPoint {
+    let half = size / 2.0;
+    let center = |corner: &ControllerPoint| ControllerPoint { x: corner.x + half, y: corner.y + half };
+    // Nearest threat for each candidate, shifted so anything overlapping is negative.
+    let margin = |corner: &ControllerPoint| threats.iter().map(|threat| threat.margin(center(corner), size)).fold(f32::MAX, f32::min);
+    let is_clear = |corner: &ControllerPoint| margin(corner) > 0.0;
+
+    let mut candidates: Vec<ControllerPoint> = arena.spawn_points.iter()
+        .map(|spawn_point| ControllerPoint { x: spawn_point.position.x - half, y: spawn_point.position.y - half })
+        .filter(|corner| !arena.check_box_collision(*corner, size))
+        .collect();
+    if !candidates.iter().any(is_clear) {
+        candidates.extend((0..RANDOM_CANDIDATES)
+            .map(|_| arena.random_free_point(size))
+            .filter(|corner| !arena.check_box_collision(*corner, size)));
+    }
+    if candidates.is_empty() {
+        return arena.random_free_point(size);
+    }
+    if candidates.iter().any(is_clear) {
+        candidates.retain(is_clear);
+    }
+    if let Some(team) = team {
+        let on_side = |corner: &ControllerPoint| is_on_side(team, arena, center(corner));
+        if candidates.iter().any(on_side) {
+            candidates.retain(on_side);
+        }
+    }
+
+    let mut rng = thread_rng();
+    match strategy {
+        SpawnStrategy::Random => *candidates.choose(&mut rng).unwrap_or(&candidates[0]),
+        SpawnStrategy::Safest | SpawnStrategy::SafeRandom => {
+            candidates.sort_by(|a, b| margin(b).total_cmp(&margin(a)));
+            let picks = if strategy == SpawnStrategy::Safest { 1 } else { SAFE_RANDOM_PICKS.min(candidates.len()) };
+            *candidates[..picks].choose(&mut rng).unwrap_or(&candidates[0])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamelogic::arena::SpawnPoint;
+    use crate::gamelogic::PLAYER_SIZE as SIZE;
+
+    fn point(x: f32, y: f32) -> ControllerPoint {
+        ControllerPoint { x, y }
+    }
+
+    /// Open arena with spawn points on the left, in the middle and on the right.
+    fn arena() -> Arena {
+        let spawn_points = [("left", 100.0), ("middle", 600.0), ("right", 1100.0)].into_iter()
+            .map(|(name, x)| SpawnPoint { name: name.to_string(), position: point(x, 400.0) })
+            .collect();
+        Arena { obstacles: Vec::new(), spawn_points, ..Arena::default_layout(1200, 800) }
+    }
+
+    /// Center of the tank spawned with its top left corner at `corner`.
+    fn center(corner: ControllerPoint) -> (f32, f32) {
+        (corner.x + SIZE / 2.0, corner.y + SIZE / 2.0)
+    }
+
+    #[test]
+    fn safest_picks_the_spawn_point_farthest_from_enemies() {
+        let threats = [Threat::tank(point(0.0, 400.0), SIZE, 1.0)];
+        assert_eq!(center(choose_spawn(SpawnStrategy::Safest, &arena(), &threats, SIZE, None)), (1100.0, 400.0));
+
+        let threats = [Threat::tank(point(1200.0, 400.0), SIZE, 1.0)];
+        assert_eq!(center(choose_spawn(SpawnStrategy::Safest, &arena(), &threats, SIZE, None)), (100.0, 400.0));
+    }
+
+    #[test]
+    fn teammates_nearby_are_fine_but_not_on_top() {
+        let enemy = Threat::tank(point(0.0, 400.0), SIZE, 1.0);
+        let threats = [enemy, Threat::teammate(point(1000.0, 400.0), SIZE)];
+        assert_eq!(center(choose_spawn(SpawnStrategy::Safest, &arena(), &threats, SIZE, None)), (1100.0, 400.0));
+
+        let threats = [enemy, Threat::teammate(point(1100.0, 400.0), SIZE)];
+        assert_eq!(center(choose_spawn(SpawnStrategy::Safest, &arena(), &threats, SIZE, None)), (600.0, 400.0));
+    }
+
+    #[test]
+    fn authored_spawn_points_are_the_only_candidates() {
+        for _ in 0..20 {
+            let (x, y) = center(choose_spawn(SpawnStrategy::Random, &arena(), &[], SIZE, None));
+            assert!([100.0, 600.0, 1100.0].contains(&x) && y == 400.0, "spawned at ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn taken_spawn_points_fall_back_to_free_space() {
+        let threats: Vec<Threat> = [100.0, 600.0, 1100.0].into_iter().map(|x| Threat::tank(point(x, 400.0), SIZE, 1.0)).collect();
+        for _ in 0..20 {
+            let corner = choose_spawn(SpawnStrategy::SafeRandom, &arena(), &threats, SIZE, None);
+            let center = ControllerPoint { x: corner.x + SIZE / 2.0, y: corner.y + SIZE / 2.0 };
+            assert!(threats.iter().all(|threat| threat.margin(center, SIZE) > 0.0), "spawned on a tank at {center:?}");
+        }
+    }
+
+    #[test]
+    fn team_side_never_beats_a_clear_spawn_point() {
+        // The left spawn point is red's only one on its side, and it is taken.
+        let threats = [Threat::tank(point(100.0, 400.0), SIZE, 1.0)];
+        for _ in 0..20 {
+            let (x, _) = center(choose_spawn(SpawnStrategy::Random, &arena(), &threats, SIZE, Some(Team::red)));
+            assert_ne!(x, 100.0);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use super::bots::BotKind;
+use crate::TankClass;
+use serde::Deserialize;
+use std::fs;
+use std::iter;
+use std::path::Path;
+
+/// The waves a survival match sends at the players, loaded from a JSON file.
+///
+/// ```json
+/// {
+///     "intermission": 8.0,
+///     "waves": [
+///         { "tanks": [{ "class": "light", "count": 2 }] },
+///         { "tanks": [{ "class": "light", "count": 2 }, { "class": "medium", "count": 1 }], "turrets": 1 }
+///     ],
+///     "escalation": { "class": "heavy", "count": 1 }
+/// }
+/// ```
+///
+/// `intermission` is the seconds between a wave being cleared and the next one rolling in.
+/// Tank `class` is `light`, `medium` or `heavy`, turrets are heavy tanks that never drive.
+/// Once the listed waves run out the last one keeps coming back, with `escalation` added on
+/// top one more time every wave.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaveSchedule {
+    intermission: f64,
+    waves: Vec<WaveDefinition>,
+    #[serde(default)]
+    escalation: Option<EnemyGroup>
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct WaveDefinition {
+    #[serde(default)]
+    tanks: Vec<EnemyGroup>,
+    #[serde(default)]
+    turrets: usize
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct EnemyGroup {
+    class: ClassDefinition,
+    count: usize
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ClassDefinition {
+    Light,
+    Medium,
+    Heavy
+}
+
+impl ClassDefinition {
+    fn to_tank_class(self) -> TankClass {
+        match self {
+            ClassDefinition::Light => TankClass::light,
+            ClassDefinition::Medium => TankClass::medium,
+            ClassDefinition::Heavy => TankClass::heavy
+        }
+    }
+}
+
+/// The schedule shipped in `waves.json`, used when no other file is loaded.
+impl Default for WaveSchedule {
+    fn default() -> Self {
+        serde_json::from_str(include_str!("../../waves.json")).expect("The built in wave schedule is valid")
+    }
+}
+
+impl WaveSchedule {
+    pub fn load(path: &Path) -> Result<WaveSchedule, String> {
+        let contents = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let schedule = serde_json::from_str::<WaveSchedule>(&contents).map_err(|error| error.to_string())?;
+        schedule.validate().map(|_| schedule)
+    }
+    fn validate(&self) -> Result<(), String> {
+        if self.intermission < 0.0 {
+            return Err("intermission can't be negative".to_string());
+        }
+        if self.waves.is_empty() {
+            return Err("at least one wave is needed".to_string());
+        }
+        if self.waves.iter().any(|wave| wave.tanks.iter().map(|group| group.count).sum::<usize>() + wave.turrets == 0) {
+            return Err("every wave needs at least one enemy".to_string());
+        }
+        Ok(())
+    }
+    /// Seconds between two waves.
+    pub(super) fn intermission(&self) -> f64 {
+        self.intermission
+    }
+    /// Everything that comes at the players in `wave`, counted from 1.
+    pub(super) fn enemies(&self, wave: i32) -> Vec<(BotKind, TankClass)> {
+        let index = (wave.max(1) - 1) as usize;
+        let last = self.waves.len() - 1;
+        let definition = &self.waves[index.min(last)];
+        let escalation = self.escalation.map(|group| EnemyGroup { count: group.count * index.saturating_sub(last), ..group });
+
+        let tanks = definition.tanks.iter().chain(escalation.iter())
+            .flat_map(|group| iter::repeat_n((BotKind::Tank, group.class.to_tank_class()), group.count));
+        let turrets = iter::repeat_n((BotKind::Turret, TankClass::heavy), definition.turrets);
+        tanks.chain(turrets).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<WaveSchedule, String> {
+        let schedule = serde_json::from_str::<WaveSchedule>(json).map_err(|error| error.to_string())?;
+        schedule.validate().map(|_| schedule)
+    }
+
+    fn count(enemies: &[(BotKind, TankClass)], kind: BotKind, class: TankClass) -> usize {
+        enemies.iter().filter(|enemy| **enemy == (kind, class)).count()
+    }
+
+    #[test]
+    fn built_in_schedule_is_valid() {
+        let schedule = WaveSchedule::default();
+        assert!(schedule.validate().is_ok());
+        assert_eq!(schedule.intermission(), 8.0);
+        assert_eq!(schedule.enemies(1), vec![(BotKind::Tank, TankClass::light); 2]);
+    }
+
+    #[test]
+    fn last_wave_repeats_with_escalation_added_every_time() {
+        let schedule = parse(r#"{
+            "intermission": 5.0,
+            "waves": [
+                { "tanks": [{ "class": "light", "count": 2 }] },
+                { "tanks": [{ "class": "medium", "count": 1 }], "turrets": 1 }
+            ],
+            "escalation": { "class": "heavy", "count": 2 }
+        }"#).expect("the schedule is valid");
+
+        let second = schedule.enemies(2);
+        assert_eq!(second.len(), 2);
+        assert_eq!(count(&second, BotKind::Tank, TankClass::heavy), 0);
+
+        for (wave, heavies) in [(3, 2), (4, 4), (6, 8)] {
+            let enemies = schedule.enemies(wave);
+            assert_eq!(count(&enemies, BotKind::Tank, TankClass::medium), 1);
+            assert_eq!(count(&enemies, BotKind::Turret, TankClass::heavy), 1);
+            assert_eq!(count(&enemies, BotKind::Tank, TankClass::heavy), heavies, "wave {wave}");
+        }
+    }
+
+    #[test]
+    fn schedule_without_escalation_repeats_the_last_wave() {
+        let schedule = parse(r#"{ "intermission": 0.0, "waves": [{ "turrets": 3 }] }"#).expect("the schedule is valid");
+        assert_eq!(schedule.enemies(10), vec![(BotKind::Turret, TankClass::heavy); 3]);
+    }
+
+    #[test]
+    fn invalid_schedules_are_rejected() {
+        assert!(parse(r#"{ "intermission": 5.0, "waves": [] }"#).is_err());
+        assert!(parse(r#"{ "intermission": -1.0, "waves": [{ "turrets": 1 }] }"#).is_err());
+        assert!(parse(r#"{ "intermission": 5.0, "waves": [{ "turrets": 1 }, { "tanks": [{ "class": "light", "count": 0 }] }] }"#).is_err());
+        assert!(parse(r#"{ "intermission": 5.0, "waves": [{ "tanks": [{ "class": "tiny", "count": 1 }] }] }"#).is_err());
+    }
+}
@@ -0,0 +1,728 @@
+use super::arena::{Arena, Normal, SweepHit};
+use super::{BitFlag, ControllerPoint, MatchSettings, ObstacleImpact, Player, PlayerInput, TANK_HEIGHT};
+use crate::WeaponType;
+use std::collections::hash_map::HashMap;
+
+const SHELL_RADIUS: f32 = 5.0;
+const GRAVITY: f32 = 1000.0;
+const LAUNCH_ELEVATION: f32 = 45.0;
+const BULLET_RADIUS: f32 = 2.0;
+const BULLET_SPEED: f32 = 1200.0;
+const BULLET_LIFETIME: f32 = 0.5;
+const BULLET_SPREAD: f32 = 4.0;
+const MINE_ARMING_TIME: f32 = 1.0;
+const MINE_LIFETIME: f32 = 30.0;
+const MINE_TRIGGER_RADIUS: f32 = 40.0;
+const ROCKET_RADIUS: f32 = 4.0;
+const ROCKET_SPEED: f32 = 400.0;
+const ROCKET_TURN_RATE: f32 = 120.0;
+const ROCKET_LIFETIME: f32 = 4.0;
+
+/// How big and how hard the explosion left behind by a weapon hits.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ExplosionSpec {
+    pub(super) max_size: i32,
+    pub(super) max_damage: f32,
+    pub(super) min_damage: f32
+}
+
+impl ExplosionSpec {
+    pub(super) fn for_weapon(weapon_type: WeaponType) -> Self {
+        match weapon_type {
+            WeaponType::machine_gun => ExplosionSpec { max_size: 20, max_damage: 12.0, min_damage: 8.0 },
+            WeaponType::mine => ExplosionSpec { max_size: 120, max_damage: 90.0, min_damage: 30.0 },
+            WeaponType::homing_rocket => ExplosionSpec { max_size: 70, max_damage: 70.0, min_damage: 20.0 },
+            WeaponType::barrel => ExplosionSpec { max_size: 140, max_damage: 80.0, min_damage: 20.0 },
+            _ => ExplosionSpec { max_size: 100, max_damage: 100.0, min_damage: 20.0 }
+        }
+    }
+}
+
+/// Where and in which direction a tank fires from.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct FiringOrigin {
+    pub(super) player_id: i32,
+    pub(super) muzzle: ControllerPoint,
+    pub(super) tank_center: ControllerPoint,
+    pub(super) cannon_angle: f32,
+    /// Multiplies the blast radius of whatever is fired.
+    pub(super) blast_scale: f32
+}
+
+/// A weapon mounted on a tank. Every weapon type fires from the same trigger inputs: holding
+/// `LoadCannon` arms (or charges) it and the `Fire` sent on release lets go. The machine gun
+/// is the exception and keeps firing for as long as the trigger is held.
+#[derive(Debug)]
+pub(super) struct Weapon {
+    pub(super) weapon_type: WeaponType,
+    /// `None` means unlimited ammo.
+    pub(super) ammo: Option<i32>,
+    pub(super) power_loaded: i32,
+    is_loading: bool,
+    cooldown: i32,
+    /// Multiplies the reload time, set by the tank class.
+    reload_factor: f32
+}
+
+impl Weapon {
+    pub(super) fn new(weapon_type: WeaponType, reload_factor: f32) -> Self {
+        Weapon {
+            weapon_type,
+            ammo: Weapon::starting_ammo(weapon_type),
+            power_loaded: 0,
+            is_loading: false,
+            cooldown: 0,
+            reload_factor
+        }
+    }
+    /// The loadout every tank spawns with.
+    pub(super) fn loadout(reload_factor: f32) -> Vec<Weapon> {
+        vec![
+            Weapon::new(WeaponType::cannon, reload_factor),
+            Weapon::new(WeaponType::machine_gun, reload_factor),
+            Weapon::new(WeaponType::mine, reload_factor),
+            Weapon::new(WeaponType::homing_rocket, reload_factor)
+        ]
+    }
+    fn starting_ammo(weapon_type: WeaponType) -> Option<i32> {
+        match weapon_type {
+            WeaponType::machine_gun => Some(120),
+            WeaponType::mine => Some(3),
+            WeaponType::homing_rocket => Some(4),
+            _ => None
+        }
+    }
+    /// Ticks between two shots. The cannon fires on every release, as it always has.
+    fn reload_time(&self) -> i32 {
+        match self.weapon_type {
+            WeaponType::machine_gun => 6,
+            WeaponType::mine => 60,
+            WeaponType::homing_rocket => 90,
+            _ => 0
+        }
+    }
+    /// How hard firing the weapon right now kicks the tank back, 1.0 for a fully loaded shell.
+    pub(super) fn recoil_power(&self) -> f32 {
+        match self.weapon_type {
+            WeaponType::machine_gun => 0.05,
+            WeaponType::mine => 0.0,
+            WeaponType::homing_rocket => 0.3,
+            _ => self.power_loaded as f32 / 100.0
+        }
+    }
+    pub(super) fn refill(&mut self) {
+        self.ammo = Weapon::starting_ammo(self.weapon_type);
+        self.cooldown = 0;
+        self.holster();
+    }
+    pub(super) fn holster(&mut self) {
+        self.is_loading = false;
+        self.power_loaded = 0;
+    }
+    pub(super) fn is_busy(&self) -> bool {
+        self.cooldown > 0 || self.is_loading
+    }
+    pub(super) fn is_loading(&self) -> bool {
+        self.is_loading
+    }
+    pub(super) fn ammo_for_output(&self) -> i32 {
+        self.ammo.unwrap_or(-1)
+    }
+    fn is_ready(&self) -> bool {
+        self.cooldown == 0 && self.ammo.is_none_or(|ammo| ammo > 0)
+    }
+    /// `reload_rate` is how many ticks of cooldown pass per tick.
+    pub(super) fn tick(&mut self, reload_rate: i32) {
+        if self.cooldown > 0 {
+            self.cooldown = (self.cooldown - reload_rate).max(0);
+        }
+        if self.weapon_type == WeaponType::cannon && self.is_loading && self.power_loaded < 100 {
+            self.power_loaded += 1;
+        }
+    }
+    /// Reads the trigger bits of `input` and returns the projectile fired this tick, if any.
+    /// A handled `Fire` is removed from `input` so it only fires once.
+    pub(super) fn trigger(&mut self, input: &mut i32, origin: FiringOrigin, settings: &MatchSettings) -> Option<Projectile> {
+        if self.weapon_type == WeaponType::machine_gun {
+            if input.contains(PlayerInput::Fire) {
+                *input -= PlayerInput::Fire as i32;
+            }
+            if input.contains(PlayerInput::LoadCannon) && self.is_ready() {
+                return Some(self.fire(origin, settings));
+            }
+            return None;
+        }
+
+        if input.contains(PlayerInput::LoadCannon) && !self.is_loading {
+            self.is_loading = true;
+            return None;
+        }
+        if input.contains(PlayerInput::Fire) && self.is_loading {
+            *input -= PlayerInput::Fire as i32;
+            let projectile = if self.is_ready() { Some(self.fire(origin, settings)) } else { None };
+            self.holster();
+            return projectile;
+        }
+        None
+    }
+    fn fire(&mut self, origin: FiringOrigin, settings: &MatchSettings) -> Projectile {
+        if let Some(ammo) = self.ammo.as_mut() {
+            *ammo -= 1;
+        }
+        self.cooldown = (self.reload_time() as f32 * self.reload_factor).round() as i32;
+
+        match self.weapon_type {
+            WeaponType::machine_gun => {
+                let spread = rand::random::<f32>() * BULLET_SPREAD * 2.0 - BULLET_SPREAD;
+                Projectile::Bullet(Bullet::new(origin.player_id, origin.muzzle, origin.cannon_angle + spread, origin.blast_scale))
+            },
+            WeaponType::mine => Projectile::Mine(Mine::new(origin.player_id, origin.tank_center, origin.blast_scale)),
+            WeaponType::homing_rocket => Projectile::Rocket(Rocket::new(origin.player_id, origin.muzzle, origin.cannon_angle, origin.blast_scale)),
+            _ => Projectile::Shell(CannonShot::new(origin.player_id, origin.muzzle, origin.cannon_angle, self.power_loaded, settings.muzzle_velocity, origin.blast_scale))
+        }
+    }
+}
+
+/// Power a shell has to be loaded with to come down `distance` px from the muzzle on flat ground,
+/// `None` if it can't reach that far.
+pub(super) fn shell_power_for(distance: f32, muzzle_velocity: f32) -> Option<i32> {
+    let speed = (distance.max(0.0) * GRAVITY / (2.0 * LAUNCH_ELEVATION.to_radians()).sin()).sqrt();
+    let power = (speed / muzzle_velocity * 100.0).ceil() as i32;
+    (power <= 100).then_some(power.max(1))
+}
+
+/// What became of a projectile during a tick.
+pub(super) enum ProjectileOutcome {
+    InFlight,
+    Detonate(ControllerPoint),
+    /// Gone without an explosion.
+    Expired
+}
+
+#[derive(Debug)]
+pub(super) enum Projectile {
+    Shell(CannonShot),
+    Bullet(Bullet),
+    Mine(Mine),
+    Rocket(Rocket)
+}
+
+impl Projectile {
+    pub(super) fn tick(&mut self, delta_time: f32, players: &HashMap<i32, Player>, settings: &MatchSettings, arena: &Arena) -> ProjectileOutcome {
+        match self {
+            Projectile::Shell(cannon_shot) => cannon_shot.tick(delta_time, players, settings, arena),
+            Projectile::Bullet(bullet) => bullet.tick(delta_time, players, arena),
+            Projectile::Mine(mine) => mine.tick(delta_time, players),
+            Projectile::Rocket(rocket) => rocket.tick(delta_time, players, arena)
+        }
+    }
+    pub(super) fn weapon_type(&self) -> WeaponType {
+        match self {
+            Projectile::Shell(_) => WeaponType::cannon,
+            Projectile::Bullet(_) => WeaponType::machine_gun,
+            Projectile::Mine(_) => WeaponType::mine,
+            Projectile::Rocket(_) => WeaponType::homing_rocket
+        }
+    }
+    pub(super) fn owner_id(&self) -> i32 {
+        match self {
+            Projectile::Shell(cannon_shot) => cannon_shot.from_player_id,
+            Projectile::Bullet(bullet) => bullet.from_player_id,
+            Projectile::Mine(mine) => mine.from_player_id,
+            Projectile::Rocket(rocket) => rocket.from_player_id
+        }
+    }
+    pub(super) fn blast_scale(&self) -> f32 {
+        match self {
+            Projectile::Shell(cannon_shot) => cannon_shot.blast_scale,
+            Projectile::Bullet(bullet) => bullet.blast_scale,
+            Projectile::Mine(mine) => mine.blast_scale,
+            Projectile::Rocket(rocket) => rocket.blast_scale
+        }
+    }
+    pub(super) fn position(&self) -> ControllerPoint {
+        match self {
+            Projectile::Shell(cannon_shot) => cannon_shot.position,
+            Projectile::Bullet(bullet) => bullet.position,
+            Projectile::Mine(mine) => mine.position,
+            Projectile::Rocket(rocket) => rocket.position
+        }
+    }
+    pub(super) fn height(&self) -> f32 {
+        match self {
+            Projectile::Shell(cannon_shot) => cannon_shot.height,
+            Projectile::Mine(_) => 0.0,
+            _ => TANK_HEIGHT / 2.0
+        }
+    }
+    /// Only shells have a launch worth reporting, everything else flies the same every time.
+    pub(super) fn launch(&self) -> Option<Launch> {
+        match self {
+            Projectile::Shell(cannon_shot) => Some(cannon_shot.launch),
+            _ => None
+        }
+    }
+    pub(super) fn count_size(&self) -> i32 {
+        match self {
+            Projectile::Shell(cannon_shot) => cannon_shot.count_size(),
+            Projectile::Bullet(_) => 6,
+            Projectile::Mine(_) => 16,
+            Projectile::Rocket(_) => 12
+        }
+    }
+}
+
+/// Tanks a projectile of `from_player_id`'s can hit or set off: anyone alive but the shooter
+/// and, in team modes, their teammates.
+fn enemies_of(players: &HashMap<i32, Player>, from_player_id: i32) -> impl Iterator<Item = &Player> {
+    let owner_team = players.get(&from_player_id).and_then(|owner| owner.team);
+    players.values().filter(move |player| player.id != from_player_id && player.is_alive() && !player.is_on_team(owner_team))
+}
+
+/// Closest point where a projectile sweeping from `from` to `to` touches an enemy tank.
+fn first_tank_hit(players: &HashMap<i32, Player>, from_player_id: i32, from: (ControllerPoint, f32), to: (ControllerPoint, f32), radius: f32) -> Option<ControllerPoint> {
+    enemies_of(players, from_player_id)
+        .filter_map(|player| player.check_swept_hit(from.0, from.1, to.0, to.1, radius))
+        .min_by(|a, b| {
+            let distance_a = (a.x - from.0.x).powi(2) + (a.y - from.0.y).powi(2);
+            let distance_b = (b.x - from.0.x).powi(2) + (b.y - from.0.y).powi(2);
+            distance_a.total_cmp(&distance_b)
+        })
+}
+
+/// Sweeps a projectile flying flat at tank height against obstacles and tanks and returns
+/// whichever it touches first.
+fn sweep_flat(players: &HashMap<i32, Player>, arena: &Arena, from_player_id: i32, from: ControllerPoint, to: ControllerPoint, radius: f32) -> Option<FlatHit> {
+    let height = TANK_HEIGHT / 2.0;
+    let obstacle_hit = arena.sweep(from, height, to, height, radius);
+    let reachable = obstacle_hit.map_or(to, |hit| hit.position);
+
+    if let Some(hit_position) = first_tank_hit(players, from_player_id, (from, height), (reachable, height), radius) {
+        return Some(FlatHit::Tank(hit_position));
+    }
+    obstacle_hit.map(|hit| FlatHit::Obstacle(hit.position))
+}
+
+enum FlatHit {
+    Tank(ControllerPoint),
+    Obstacle(ControllerPoint)
+}
+
+/// Where a shell was fired from and how much power it was loaded with.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Launch {
+    pub(super) origin: ControllerPoint,
+    pub(super) power: i32
+}
+
+impl Launch {
+    /// Straight line px from the muzzle, however the shell got there.
+    pub(super) fn distance_to(&self, point: ControllerPoint) -> i32 {
+        ((point.x - self.origin.x).powi(2) + (point.y - self.origin.y).powi(2)).sqrt().round() as i32
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct CannonShot {
+    from_player_id: i32,
+    /// Blast radius multiplier of the tank when it fired, a power up running out mid flight doesn't shrink it.
+    blast_scale: f32,
+    launch: Launch,
+    position: ControllerPoint,
+    previous_position: ControllerPoint,
+    height: f32,
+    previous_height: f32,
+    apex_height: f32,
+    velocity_x: f32,
+    velocity_y: f32,
+    velocity_z: f32
+}
+
+impl CannonShot {
+    fn count_size(&self) -> i32 {
+        if self.apex_height <= 0.0 {
+            return 20;
+        }
+        20 + (80.0 * self.height / self.apex_height) as i32
+    }
+    fn tick(&mut self, delta_time: f32, players: &HashMap<i32, Player>, settings: &MatchSettings, arena: &Arena) -> ProjectileOutcome {
+        let mut has_landed = self.fly(delta_time, settings.wall_restitution, arena);
+        let obstacle_hit = arena.sweep(self.previous_position, self.previous_height, self.position, self.height, SHELL_RADIUS);
+
+        if settings.detonation_rule.detonates_on_impact() {
+            let from = (self.previous_position, self.previous_height);
+            let reachable = obstacle_hit.map_or((self.position, self.height), |hit| (hit.position, self.height_at(hit.t)));
+            if let Some(hit_position) = first_tank_hit(players, self.from_player_id, from, reachable, SHELL_RADIUS) {
+                return ProjectileOutcome::Detonate(hit_position);
+            }
+        }
+
+        if let Some(hit) = obstacle_hit {
+            match hit.normal {
+                Some(normal) if settings.obstacle_impact == ObstacleImpact::Ricochet => self.ricochet(hit, normal, settings.wall_restitution),
+                Some(_) => return ProjectileOutcome::Detonate(hit.position),
+                None => {
+                    self.position = hit.position;
+                    has_landed = true;
+                }
+            }
+        }
+
+        if has_landed {
+            if settings.detonation_rule.detonates_in_air() {
+                return ProjectileOutcome::Detonate(self.position);
+            }
+            return ProjectileOutcome::Expired;
+        }
+        ProjectileOutcome::InFlight
+    }
+    /// Advances the shell by `delta_time` seconds and returns true once it has landed. Height follows
+    /// the closed form of a constant gravity arc and the step is cut short on landing, so the path is
+    /// the same at any tick rate.
+    fn fly(&mut self, delta_time: f32, wall_restitution: f32, arena: &Arena) -> bool {
+        self.previous_position = self.position;
+        self.previous_height = self.height;
+
+        let time_to_land = (self.velocity_z + (self.velocity_z.powi(2) + 2.0 * GRAVITY * self.height).sqrt()) / GRAVITY;
+        let time_step = time_to_land.min(delta_time);
+
+        self.position.translate(self.velocity_x * time_step, self.velocity_y * time_step);
+        self.height = (self.height + self.velocity_z * time_step - 0.5 * GRAVITY * time_step.powi(2)).max(0.0);
+        self.velocity_z -= GRAVITY * time_step;
+
+        self.bounce_off_walls(wall_restitution, arena);
+        time_to_land <= delta_time
+    }
+    fn height_at(&self, t: f32) -> f32 {
+        self.previous_height + (self.height - self.previous_height) * t
+    }
+    /// Reflects the rest of this step off the side of an obstacle, losing speed like on the arena edge.
+    fn ricochet(&mut self, hit: SweepHit, (normal_x, normal_y): Normal, wall_restitution: f32) {
+        let (remaining_x, remaining_y) = (self.position.x - hit.position.x, self.position.y - hit.position.y);
+        let remaining_into_obstacle = remaining_x * normal_x + remaining_y * normal_y;
+        let velocity_into_obstacle = self.velocity_x * normal_x + self.velocity_y * normal_y;
+
+        self.position = ControllerPoint {
+            x: hit.position.x + (remaining_x - 2.0 * remaining_into_obstacle * normal_x) * wall_restitution,
+            y: hit.position.y + (remaining_y - 2.0 * remaining_into_obstacle * normal_y) * wall_restitution
+        };
+        self.velocity_x = (self.velocity_x - 2.0 * velocity_into_obstacle * normal_x) * wall_restitution;
+        self.velocity_y = (self.velocity_y - 2.0 * velocity_into_obstacle * normal_y) * wall_restitution;
+    }
+    /// Mirrors the part of the step that went past a wall. The overshoot is scaled by the
+    /// restitution too, since it was travelled after the bounce at the reduced speed.
+    fn bounce_off_walls(&mut self, wall_restitution: f32, arena: &Arena) {
+        let (max_x, max_y) = (arena.width as f32, arena.height as f32);
+
+        if self.position.x < 0.0 {
+            self.position.x = -self.position.x * wall_restitution;
+            self.velocity_x = self.velocity_x.abs() * wall_restitution;
+        } else if self.position.x > max_x {
+            self.position.x = max_x - (self.position.x - max_x) * wall_restitution;
+            self.velocity_x = -self.velocity_x.abs() * wall_restitution;
+        }
+
+        if self.position.y < 0.0 {
+            self.position.y = -self.position.y * wall_restitution;
+            self.velocity_y = self.velocity_y.abs() * wall_restitution;
+        } else if self.position.y > max_y {
+            self.position.y = max_y - (self.position.y - max_y) * wall_restitution;
+            self.velocity_y = -self.velocity_y.abs() * wall_restitution;
+        }
+    }
+    fn new(from_player_id: i32, from: ControllerPoint, angle: f32, power: i32, muzzle_velocity: f32, blast_scale: f32) -> Self {
+        let speed = muzzle_velocity * (power as f32 / 100.0);
+        let elevation = LAUNCH_ELEVATION.to_radians();
+        let horizontal_speed = speed * elevation.cos();
+        let velocity_z = speed * elevation.sin();
+        let radians = angle.to_radians();
+
+        CannonShot {
+            from_player_id,
+            blast_scale,
+            launch: Launch { origin: from, power },
+            position: from,
+            previous_position: from,
+            height: 0.0,
+            previous_height: 0.0,
+            apex_height: velocity_z.powi(2) / (2.0 * GRAVITY),
+            velocity_x: horizontal_speed * radians.cos(),
+            velocity_y: horizontal_speed * radians.sin(),
+            velocity_z
+        }
+    }
+}
+
+/// Machine gun round. Flies flat and fast and always goes off on contact.
+#[derive(Debug)]
+pub(super) struct Bullet {
+    from_player_id: i32,
+    blast_scale: f32,
+    position: ControllerPoint,
+    velocity_x: f32,
+    velocity_y: f32,
+    time_left: f32
+}
+
+impl Bullet {
+    fn new(from_player_id: i32, from: ControllerPoint, angle: f32, blast_scale: f32) -> Self {
+        let radians = angle.to_radians();
+        Bullet {
+            from_player_id,
+            blast_scale,
+            position: from,
+            velocity_x: BULLET_SPEED * radians.cos(),
+            velocity_y: BULLET_SPEED * radians.sin(),
+            time_left: BULLET_LIFETIME
+        }
+    }
+    fn tick(&mut self, delta_time: f32, players: &HashMap<i32, Player>, arena: &Arena) -> ProjectileOutcome {
+        let from = self.position;
+        self.position.translate(self.velocity_x * delta_time, self.velocity_y * delta_time);
+        self.time_left -= delta_time;
+
+        match sweep_flat(players, arena, self.from_player_id, from, self.position, BULLET_RADIUS) {
+            Some(FlatHit::Tank(hit_position)) => return ProjectileOutcome::Detonate(hit_position),
+            Some(FlatHit::Obstacle(_)) => return ProjectileOutcome::Expired,
+            None => ()
+        }
+        if self.time_left <= 0.0 || arena.is_out_of_bounds(self.position) {
+            return ProjectileOutcome::Expired;
+        }
+        ProjectileOutcome::InFlight
+    }
+}
+
+/// Sits where it was dropped and goes off once an enemy tank comes close after it has armed.
+#[derive(Debug)]
+pub(super) struct Mine {
+    from_player_id: i32,
+    blast_scale: f32,
+    position: ControllerPoint,
+    arming_time_left: f32,
+    time_left: f32
+}
+
+impl Mine {
+    fn new(from_player_id: i32, position: ControllerPoint, blast_scale: f32) -> Self {
+        Mine {
+            from_player_id,
+            blast_scale,
+            position,
+            arming_time_left: MINE_ARMING_TIME,
+            time_left: MINE_LIFETIME
+        }
+    }
+    fn tick(&mut self, delta_time: f32, players: &HashMap<i32, Player>) -> ProjectileOutcome {
+        self.time_left -= delta_time;
+        if self.time_left <= 0.0 {
+            return ProjectileOutcome::Expired;
+        }
+        if self.arming_time_left > 0.0 {
+            self.arming_time_left -= delta_time;
+            return ProjectileOutcome::InFlight;
+        }
+
+        let triggered = enemies_of(players, self.from_player_id)
+            .any(|player| {
+                let center = player.center();
+                (center.x - self.position.x).powi(2) + (center.y - self.position.y).powi(2) <= MINE_TRIGGER_RADIUS.powi(2)
+            });
+
+        if triggered {
+            return ProjectileOutcome::Detonate(self.position);
+        }
+        ProjectileOutcome::InFlight
+    }
+}
+
+/// Steers towards the closest enemy tank at a limited turn rate.
+#[derive(Debug)]
+pub(super) struct Rocket {
+    from_player_id: i32,
+    blast_scale: f32,
+    position: ControllerPoint,
+    angle: f32,
+    time_left: f32
+}
+
+impl Rocket {
+    fn new(from_player_id: i32, from: ControllerPoint, angle: f32, blast_scale: f32) -> Self {
+        Rocket {
+            from_player_id,
+            blast_scale,
+            position: from,
+            angle,
+            time_left: ROCKET_LIFETIME
+        }
+    }
+    fn tick(&mut self, delta_time: f32, players: &HashMap<i32, Player>, arena: &Arena) -> ProjectileOutcome {
+        self.steer(delta_time, players);
+
+        let from = self.position;
+        let radians = self.angle.to_radians();
+        self.position.translate(ROCKET_SPEED * delta_time * radians.cos(), ROCKET_SPEED * delta_time * radians.sin());
+        self.time_left -= delta_time;
+
+        match sweep_flat(players, arena, self.from_player_id, from, self.position, ROCKET_RADIUS) {
+            Some(FlatHit::Tank(hit_position)) | Some(FlatHit::Obstacle(hit_position)) => return ProjectileOutcome::Detonate(hit_position),
+            None => ()
+        }
+        if self.time_left <= 0.0 || arena.is_out_of_bounds(self.position) {
+            return ProjectileOutcome::Detonate(self.position);
+        }
+        ProjectileOutcome::InFlight
+    }
+    fn steer(&mut self, delta_time: f32, players: &HashMap<i32, Player>) {
+        let target = enemies_of(players, self.from_player_id)
+            .filter(|player| !player.is_concealed)
+            .map(Player::center)
+            .min_by(|a, b| {
+                let distance_a = (a.x - self.position.x).powi(2) + (a.y - self.position.y).powi(2);
+                let distance_b = (b.x - self.position.x).powi(2) + (b.y - self.position.y).powi(2);
+                distance_a.total_cmp(&distance_b)
+            });
+
+        let Some(target) = target else {
+            return;
+        };
+
+        let target_angle = (target.y - self.position.y).atan2(target.x - self.position.x).to_degrees();
+        let mut diff = target_angle - self.angle;
+        while diff > 180.0 {
+            diff -= 360.0;
+        }
+        while diff < -180.0 {
+            diff += 360.0;
+        }
+        let max_turn = ROCKET_TURN_RATE * delta_time;
+        self.angle += diff.clamp(-max_turn, max_turn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AbilityType, PlayerInGameStatus, TankClass};
+
+    fn tank(id: i32, x: f32, y: f32) -> Player {
+        let mut player = Player::new(id, ControllerPoint { x, y }, 0.0, AbilityType::empty_15, TankClass::medium, None);
+        player.player_in_game_status = PlayerInGameStatus::alive;
+        player
+    }
+
+    fn point(x: f32, y: f32) -> ControllerPoint {
+        ControllerPoint { x, y }
+    }
+
+    #[test]
+    fn cannon_fires_on_every_release() {
+        let origin = FiringOrigin { player_id: 1, muzzle: point(0.0, 0.0), tank_center: point(0.0, 0.0), cannon_angle: 0.0, blast_scale: 1.0 };
+        let settings = MatchSettings::default();
+        let mut cannon = Weapon::new(WeaponType::cannon, 1.3);
+        for _ in 0..2 {
+            let mut input = PlayerInput::LoadCannon as i32;
+            assert!(cannon.trigger(&mut input, origin, &settings).is_none());
+            let mut input = PlayerInput::Fire as i32;
+            assert!(matches!(cannon.trigger(&mut input, origin, &settings), Some(Projectile::Shell(_))));
+            assert!(!cannon.is_busy());
+        }
+    }
+
+    #[test]
+    fn swept_hit_catches_a_shell_that_skips_over_the_tank_in_one_step() {
+        let players = HashMap::from([(2, tank(2, 100.0, 0.0))]);
+        let hit = first_tank_hit(&players, 1, (point(0.0, 20.0), 10.0), (point(300.0, 20.0), 10.0), SHELL_RADIUS);
+        let hit = hit.expect("the shell passes straight through the tank");
+        assert_eq!(hit.x, 100.0 - SHELL_RADIUS);
+        assert_eq!(hit.y, 20.0);
+    }
+
+    #[test]
+    fn swept_hit_misses_a_shell_flying_over_the_tank() {
+        let players = HashMap::from([(2, tank(2, 100.0, 0.0))]);
+        let hit = first_tank_hit(&players, 1, (point(0.0, 20.0), 200.0), (point(300.0, 20.0), 200.0), SHELL_RADIUS);
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn swept_hit_picks_the_closest_tank_and_never_the_shooter() {
+        let players = HashMap::from([
+            (1, tank(1, 0.0, 0.0)),
+            (2, tank(2, 300.0, 0.0)),
+            (3, tank(3, 150.0, 0.0))
+        ]);
+        let hit = first_tank_hit(&players, 1, (point(20.0, 20.0), 10.0), (point(500.0, 20.0), 10.0), SHELL_RADIUS);
+        assert_eq!(hit.map(|hit| hit.x), Some(150.0 - SHELL_RADIUS));
+    }
+
+    #[test]
+    fn swept_hit_passes_teammates_and_hits_the_enemy_behind_them() {
+        let mut shooter = tank(1, 0.0, 0.0);
+        shooter.team = Some(crate::Team::red);
+        let mut teammate = tank(2, 100.0, 0.0);
+        teammate.team = Some(crate::Team::red);
+        let mut enemy = tank(3, 200.0, 0.0);
+        enemy.team = Some(crate::Team::blue);
+        let mut players = HashMap::from([(1, shooter), (2, teammate)]);
+
+        let hit = first_tank_hit(&players, 1, (point(60.0, 20.0), 10.0), (point(300.0, 20.0), 10.0), SHELL_RADIUS);
+        assert!(hit.is_none());
+
+        players.insert(3, enemy);
+        let hit = first_tank_hit(&players, 1, (point(60.0, 20.0), 10.0), (point(300.0, 20.0), 10.0), SHELL_RADIUS);
+        assert_eq!(hit.map(|hit| hit.x), Some(200.0 - SHELL_RADIUS));
+    }
+
+    fn open_field() -> Arena {
+        Arena { obstacles: Vec::new(), ..Arena::default_layout(4000, 4000) }
+    }
+
+    /// Flies a shell fired east from (100, 2000) until it lands and returns where it came down
+    /// and the highest it got.
+    fn land(shell: &mut CannonShot, delta_time: f32) -> (ControllerPoint, f32) {
+        let arena = open_field();
+        let mut peak: f32 = 0.0;
+        for _ in 0..10_000 {
+            let has_landed = shell.fly(delta_time, 1.0, &arena);
+            peak = peak.max(shell.height);
+            if has_landed {
+                return (shell.position, peak);
+            }
+        }
+        panic!("the shell never came down");
+    }
+
+    #[test]
+    fn shell_fired_with_the_power_for_a_distance_lands_about_that_far_away() {
+        let muzzle_velocity = 1000.0;
+        let power = shell_power_for(600.0, muzzle_velocity).expect("600 px is in range");
+        let mut shell = CannonShot::new(1, point(100.0, 2000.0), 0.0, power, muzzle_velocity, 1.0);
+        let (landed, _) = land(&mut shell, 1.0 / 60.0);
+
+        // The power is rounded up to a whole percent, so it may carry a little further.
+        assert!(landed.x - 100.0 >= 600.0 - 1.0 && landed.x - 100.0 < 600.0 * 1.03, "landed at {}", landed.x);
+        assert!((landed.y - 2000.0).abs() < 0.01);
+        assert_eq!(shell.height, 0.0);
+    }
+
+    #[test]
+    fn shell_peaks_at_its_apex_height() {
+        let mut shell = CannonShot::new(1, point(100.0, 2000.0), 0.0, 80, 1000.0, 1.0);
+        let apex_height = shell.apex_height;
+        // Twenty steps to the top, so one of them ends right on it.
+        let time_to_apex = shell.velocity_z / GRAVITY;
+        let (_, peak) = land(&mut shell, time_to_apex / 20.0);
+
+        assert!((peak - apex_height).abs() < apex_height * 0.001, "peaked at {peak}, expected {apex_height}");
+    }
+
+    #[test]
+    fn shell_lands_in_the_same_place_at_any_tick_rate() {
+        let (fine, _) = land(&mut CannonShot::new(1, point(100.0, 2000.0), 0.0, 70, 1000.0, 1.0), 1.0 / 240.0);
+        let (coarse, _) = land(&mut CannonShot::new(1, point(100.0, 2000.0), 0.0, 70, 1000.0, 1.0), 1.0 / 10.0);
+
+        assert!((fine.x - coarse.x).abs() < 0.5, "{} vs {}", fine.x, coarse.x);
+        assert!((fine.y - coarse.y).abs() < 0.5);
+    }
+}
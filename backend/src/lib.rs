@@ -1,18 +1,54 @@
-include!(concat!(env!("OUT_DIR"), "/messages.rs"));
+// Generated by protoc-rust, which follows neither the naming nor the clippy style of the rest of the crate.
+#[allow(non_camel_case_types, non_snake_case, non_upper_case_globals, mismatched_lifetime_syntaxes, unused_parens)]
+#[allow(clippy::bool_comparison, clippy::derivable_impls, clippy::match_like_matches_macro, clippy::mem_replace_with_default, clippy::needless_borrow, clippy::redundant_closure, clippy::redundant_static_lifetimes, clippy::vec_init_then_push)]
+mod messages {
+    include!(concat!(env!("OUT_DIR"), "/messages.rs"));
+}
+pub use messages::*;
 
 pub mod gamelogic {
-    use crate::{CannonEventResponse, ClientInfo, ClientLobbyStatus, ClientStatus, GameControllerStatus, InputRequest, MessageType, PlayerInGameResponse, PlayerInGameStatus, Point, ServerGameFrameResponse, ServerLobbyResponse};
+    use crate::{AbilityType, CannonEventResponse, TankClass, ClientInfo, MapInfo, ClientLobbyStatus, ClientStatus, DetonationRuleType, GameControllerStatus, GameEvent, GameModeType, GameEventType, InputRequest, MatchSettingsInfo, MessageType, ObstacleImpactType, ObstacleMaterial, PlayerInGameResponse, PlayerInGameStatus, Point, PowerUpType, ServerArenaResponse, ServerGameEventsResponse, ServerGameFrameResponse, ServerLobbyResponse, SpawnStrategyType, Team, TeamScore, TieBreakType, WeaponType};
     use std::collections::VecDeque;
     use std::collections::hash_map::HashMap;
     use protobuf::RepeatedField;
     use rand::{thread_rng, Rng};
+    use abilities::{Ability, SmokeCloud};
+    use arena::{Arena, Base, Obstacle, Traction};
+    use classes::TankStats;
+    use modes::{GameMode, MatchResult};
+    pub use generator::GeneratorSettings;
+    pub use maps::MapDefinition;
+    pub use scoring::ScoringRules;
+    pub use spawns::SpawnStrategy;
+    pub use waves::WaveSchedule;
+    use spawns::Threat;
+    use powerups::{ActiveEffect, PowerUp};
+    use weapons::{ExplosionSpec, FiringOrigin, Launch, Projectile, ProjectileOutcome, Weapon};
+
+    mod abilities;
+    mod arena;
+    mod bots;
+    mod classes;
+    mod generator;
+    mod maps;
+    mod modes;
+    mod powerups;
+    mod scoring;
+    mod spawns;
+    mod waves;
+    mod weapons;
 
     const ANGLE_EASING_FACTOR: f32 = 0.2;
     const PLAYER_SIZE: f32 = 40.0;
     const CANNON_LENGTH: f32 = 40.0;
-    const MAX_CANNON_SHOT_LENGTH: i32 = 300;
     const BOUNDS_HEIGHT: i32 = 800;
     const BOUNDS_WIDTH: i32 = 1200;
+    const TANK_HEIGHT: f32 = 20.0;
+    const MAX_HEALTH: i32 = 100;
+    /// Slowest shells a match can be set up with, any slower and they barely leave the cannon.
+    const MIN_MUZZLE_VELOCITY: f32 = 100.0;
+    /// Most armor a match can be set up with, so tanks can still be destroyed.
+    const MAX_ARMOR: f32 = 0.9;
     #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
     enum PlayerInput {
         NoInput,
@@ -23,7 +59,10 @@ pub mod gamelogic {
         AimPositive = 1 << 4,
         AimNegative = 1 << 5,
         LoadCannon = 1 << 6,
-        Fire = 1 << 7
+        Fire = 1 << 7,
+        NextWeapon = 1 << 8,
+        PreviousWeapon = 1 << 9,
+        UseAbility = 1 << 10
     }
     trait BitFlag {
         fn contains(&self, player_input:PlayerInput) -> bool;
@@ -52,7 +91,7 @@ pub mod gamelogic {
                 y: thread_rng().gen_range(0..=height_bounds) as f32
             }
         }
-        pub fn to_buffer_point(&self) -> Point {
+        pub fn to_buffer_point(self) -> Point {
             let mut point = Point::new();
             point.set_x(self.x as i32);
             point.set_y(self.y as i32);
@@ -60,35 +99,211 @@ pub mod gamelogic {
             point
         }
     }
+    #[derive(Debug, Clone, Copy)]
+    enum ControllerEvent {
+        /// `killer_id` is 0 and `weapon` empty when the game mode itself destroyed the tank.
+        Kill { killer_id: i32, victim_id: i32, weapon: WeaponType, position: ControllerPoint },
+        ShotFired { player_id: i32, weapon: WeaponType, position: ControllerPoint },
+        Respawn { player_id: i32, position: ControllerPoint },
+        MatchStart,
+        /// `winner_id` is 0 when a team won, nobody did or it was a draw.
+        MatchEnd { winner_id: i32, winning_team: Option<Team>, draw: bool },
+        /// Time ran out with the lead shared, the next to pull ahead wins.
+        Overtime,
+        RoundStart { round: i32 },
+        /// `winner_id` is 0 when the last tanks took each other out.
+        RoundEnd { winner_id: i32, round: i32 },
+        /// `team` is the flag's team in every flag event.
+        FlagTaken { player_id: i32, team: Team, position: ControllerPoint },
+        FlagDropped { player_id: i32, team: Team, position: ControllerPoint },
+        /// `player_id` is 0 when the flag went back on its own.
+        FlagReturned { player_id: i32, team: Team, position: ControllerPoint },
+        FlagCaptured { player_id: i32, team: Team, position: ControllerPoint },
+        HillMoved { position: ControllerPoint },
+        WaveStart { wave: i32 },
+        WaveCleared { wave: i32 },
+        /// Where a shell came down, how far from the muzzle and at what power. `target_id` and
+        /// `damage` are 0 when it hurt nobody.
+        HitMarker { player_id: i32, target_id: i32, position: ControllerPoint, distance: i32, power: i32, damage: i32 },
+        ObstacleDamaged { obstacle_id: i32, player_id: i32, hit_points: i32 },
+        ObstacleDestroyed { obstacle_id: i32, player_id: i32, position: ControllerPoint },
+        PowerUpCollected { player_id: i32, power_up: PowerUpType, position: ControllerPoint }
+    }
+
+    impl ControllerEvent {
+        pub fn to_buffer_event(self) -> GameEvent {
+            let mut event = GameEvent::new();
+            match self {
+                ControllerEvent::Kill { killer_id, victim_id, weapon, position } => {
+                    event.set_field_type(GameEventType::kill);
+                    event.set_player_id(killer_id);
+                    event.set_target_id(victim_id);
+                    event.set_weapon(weapon);
+                    event.set_position(position.to_buffer_point());
+                },
+                ControllerEvent::ShotFired { player_id, weapon, position } => {
+                    event.set_field_type(GameEventType::shot_fired);
+                    event.set_player_id(player_id);
+                    event.set_weapon(weapon);
+                    event.set_position(position.to_buffer_point());
+                },
+                ControllerEvent::Respawn { player_id, position } => {
+                    event.set_field_type(GameEventType::respawn);
+                    event.set_player_id(player_id);
+                    event.set_position(position.to_buffer_point());
+                },
+                ControllerEvent::MatchStart => {
+                    event.set_field_type(GameEventType::match_start);
+                },
+                ControllerEvent::MatchEnd { winner_id, winning_team, draw } => {
+                    event.set_field_type(GameEventType::match_end);
+                    event.set_player_id(winner_id);
+                    event.set_team(winning_team.unwrap_or(Team::empty_19));
+                    event.set_draw(draw);
+                },
+                ControllerEvent::Overtime => {
+                    event.set_field_type(GameEventType::overtime);
+                },
+                ControllerEvent::RoundStart { round } => {
+                    event.set_field_type(GameEventType::round_start);
+                    event.set_round(round);
+                },
+                ControllerEvent::RoundEnd { winner_id, round } => {
+                    event.set_field_type(GameEventType::round_end);
+                    event.set_player_id(winner_id);
+                    event.set_round(round);
+                },
+                ControllerEvent::FlagTaken { player_id, team, position } => {
+                    event.set_field_type(GameEventType::flag_taken);
+                    event.set_player_id(player_id);
+                    event.set_team(team);
+                    event.set_position(position.to_buffer_point());
+                },
+                ControllerEvent::FlagDropped { player_id, team, position } => {
+                    event.set_field_type(GameEventType::flag_dropped);
+                    event.set_player_id(player_id);
+                    event.set_team(team);
+                    event.set_position(position.to_buffer_point());
+                },
+                ControllerEvent::FlagReturned { player_id, team, position } => {
+                    event.set_field_type(GameEventType::flag_returned);
+                    event.set_player_id(player_id);
+                    event.set_team(team);
+                    event.set_position(position.to_buffer_point());
+                },
+                ControllerEvent::FlagCaptured { player_id, team, position } => {
+                    event.set_field_type(GameEventType::flag_captured);
+                    event.set_player_id(player_id);
+                    event.set_team(team);
+                    event.set_position(position.to_buffer_point());
+                },
+                ControllerEvent::HillMoved { position } => {
+                    event.set_field_type(GameEventType::hill_moved);
+                    event.set_position(position.to_buffer_point());
+                },
+                ControllerEvent::WaveStart { wave } => {
+                    event.set_field_type(GameEventType::wave_start);
+                    event.set_wave(wave);
+                },
+                ControllerEvent::WaveCleared { wave } => {
+                    event.set_field_type(GameEventType::wave_cleared);
+                    event.set_wave(wave);
+                },
+                ControllerEvent::HitMarker { player_id, target_id, position, distance, power, damage } => {
+                    event.set_field_type(GameEventType::hit_marker);
+                    event.set_player_id(player_id);
+                    event.set_target_id(target_id);
+                    event.set_position(position.to_buffer_point());
+                    event.set_distance(distance);
+                    event.set_power(power);
+                    event.set_damage(damage);
+                },
+                ControllerEvent::ObstacleDamaged { obstacle_id, player_id, hit_points } => {
+                    event.set_field_type(GameEventType::obstacle_damaged);
+                    event.set_obstacle_id(obstacle_id);
+                    event.set_player_id(player_id);
+                    event.set_hit_points(hit_points);
+                },
+                ControllerEvent::ObstacleDestroyed { obstacle_id, player_id, position } => {
+                    event.set_field_type(GameEventType::obstacle_destroyed);
+                    event.set_obstacle_id(obstacle_id);
+                    event.set_player_id(player_id);
+                    event.set_position(position.to_buffer_point());
+                },
+                ControllerEvent::PowerUpCollected { player_id, power_up, position } => {
+                    event.set_field_type(GameEventType::power_up_collected);
+                    event.set_player_id(player_id);
+                    event.set_power_up(power_up);
+                    event.set_position(position.to_buffer_point());
+                }
+            }
+            event
+        }
+    }
+
     struct Explosion {
         position: ControllerPoint,
         from_player_id: i32,
+        weapon: WeaponType,
+        spec: ExplosionSpec,
         size: i32,
-        reached_max_size: bool
+        reached_max_size: bool,
+        damaged_player_ids: Vec<i32>,
+        damaged_obstacle_ids: Vec<i32>,
+        /// Set for shells, so the hit markers can tell how the shot was made.
+        launch: Option<Launch>,
+        /// Whether it has hurt a tank, for the hit markers.
+        has_hit: bool
     }
 
     impl Explosion {
-        pub fn new(from_player_id: i32, position: ControllerPoint) -> Self {
+        pub fn new(from_player_id: i32, weapon: WeaponType, position: ControllerPoint) -> Self {
             Explosion {
                 from_player_id,
+                weapon,
+                spec: ExplosionSpec::for_weapon(weapon),
                 position,
                 size: 1,
-                reached_max_size: false
+                reached_max_size: false,
+                damaged_player_ids: Vec::new(),
+                damaged_obstacle_ids: Vec::new(),
+                launch: None,
+                has_hit: false
             }
         }
+        /// Grows the blast, used for the blast radius power up.
+        pub fn scaled(mut self, scale: f32) -> Self {
+            self.spec.max_size = (self.spec.max_size as f32 * scale) as i32;
+            self
+        }
+        /// Remembers how the shell that left the blast was fired.
+        pub fn launched(mut self, launch: Option<Launch>) -> Self {
+            self.launch = launch;
+            self
+        }
+        fn hit_marker(&self, target_id: i32, damage: i32) -> Option<ControllerEvent> {
+            self.launch.map(|launch| ControllerEvent::HitMarker {
+                player_id: self.from_player_id,
+                target_id,
+                position: self.position,
+                distance: launch.distance_to(self.position),
+                power: launch.power,
+                damage
+            })
+        }
         pub fn tick(&mut self) {
 
-            if !self.reached_max_size && self.size >= 100 {
+            if !self.reached_max_size && self.size >= self.spec.max_size {
                 self.reached_max_size = true;
             }
 
             if self.reached_max_size {
-                self.size -= 20;
+                self.size -= self.spec.max_size / 5;
                 return;
             }
-            self.size += 10;
+            self.size += self.spec.max_size / 10;
         }
-        pub fn check_for_hit(&self, player_pos: ControllerPoint) -> bool {
+        pub fn check_for_hit(&self, player_pos: ControllerPoint, player_size: f32) -> bool {
             let self_x = self.position.x;
             let self_y = self.position.y;
             let current_explosion_radius = (self.size / 2)as f32;
@@ -99,21 +314,21 @@ pub mod gamelogic {
             let max_possible_y = self_y + current_explosion_radius;
 
             if max_possible_x < player_pos.x || 
-            min_possible_x > player_pos.x + PLAYER_SIZE ||
+            min_possible_x > player_pos.x + player_size ||
             max_possible_y < player_pos.y || 
-            min_possible_y > player_pos.y + PLAYER_SIZE {
+            min_possible_y > player_pos.y + player_size {
                 return false;
             }
 
             if self_x >= player_pos.x && 
-            self_x <= player_pos.x + PLAYER_SIZE && 
+            self_x <= player_pos.x + player_size && 
             self_y <= player_pos.y && 
-            self_y >= player_pos.y + PLAYER_SIZE {
+            self_y >= player_pos.y + player_size {
                 return true;
             }   
 
-            let closest_x = self_x.max(player_pos.x).min(player_pos.x + PLAYER_SIZE);
-            let closest_y = self_y.max(player_pos.y).min(player_pos.y + PLAYER_SIZE);
+            let closest_x = self_x.max(player_pos.x).min(player_pos.x + player_size);
+            let closest_y = self_y.max(player_pos.y).min(player_pos.y + player_size);
 
             let distance_x = self_x - closest_x;
             let distance_y = self_y - closest_y;
@@ -122,8 +337,8 @@ pub mod gamelogic {
                 return true;
             }
 
-            for &x in &[player_pos.x, player_pos.x + PLAYER_SIZE] {
-                for &y in &[player_pos.y, player_pos.y + PLAYER_SIZE] {
+            for &x in &[player_pos.x, player_pos.x + player_size] {
+                for &y in &[player_pos.y, player_pos.y + player_size] {
                     let dx = self_x - x;
                     let dy = self_y - y;
                     if dx.powi(2) + dy.powi(2) <= current_explosion_radius.powi(2) {
@@ -134,85 +349,214 @@ pub mod gamelogic {
 
             false
         }
-    }
+        /// Damage dealt to a tank the explosion has reached, falling off linearly from the center
+        /// to the edge of the fully grown blast. Each explosion damages a tank only once.
+        pub fn check_for_damage(&mut self, player_id: i32, player_pos: ControllerPoint, player_size: f32) -> Option<f32> {
+            if self.damaged_player_ids.contains(&player_id) || !self.check_for_hit(player_pos, player_size) {
+                return None;
+            }
+            self.damaged_player_ids.push(player_id);
 
-    #[derive(Debug)]
-    struct CannonShot {
-        from_player_id: i32,
-        distance_to_travel: f32,
-        trajectory: VecDeque<ControllerPoint>,
-        last_position: Option<ControllerPoint>,
-        position: Option<ControllerPoint>
+            Some(self.spec.min_damage + (self.spec.max_damage - self.spec.min_damage) * self.falloff(player_pos, player_size))
+        }
+        /// 1.0 for a tank at the center of the fully grown blast down to 0.0 at its edge.
+        fn falloff(&self, player_pos: ControllerPoint, player_size: f32) -> f32 {
+            let closest_x = self.position.x.max(player_pos.x).min(player_pos.x + player_size);
+            let closest_y = self.position.y.max(player_pos.y).min(player_pos.y + player_size);
+            let distance = ((self.position.x - closest_x).powi(2) + (self.position.y - closest_y).powi(2)).sqrt();
+            (1.0 - distance / (self.spec.max_size / 2) as f32).clamp(0.0, 1.0)
+        }
+        /// Push away from the center for a tank the explosion has reached, falling off like the damage.
+        pub fn knockback(&self, player_pos: ControllerPoint, player_size: f32, strength: f32) -> (f32, f32) {
+            let center = ControllerPoint { x: player_pos.x + player_size / 2.0, y: player_pos.y + player_size / 2.0 };
+            let (away_x, away_y) = (center.x - self.position.x, center.y - self.position.y);
+            let length = (away_x.powi(2) + away_y.powi(2)).sqrt();
+            if length < f32::EPSILON {
+                return (0.0, 0.0);
+            }
+            let push = strength * self.falloff(player_pos, player_size);
+            (away_x / length * push, away_y / length * push)
+        }
+        /// Same falloff as for tanks, measured to the closest point of the obstacle.
+        pub fn check_for_obstacle_damage(&mut self, obstacle: &Obstacle) -> Option<f32> {
+            if !obstacle.is_destructible() || self.damaged_obstacle_ids.contains(&obstacle.id) {
+                return None;
+            }
+            let distance = obstacle.distance_to(self.position);
+            if distance > (self.size / 2) as f32 {
+                return None;
+            }
+            self.damaged_obstacle_ids.push(obstacle.id);
+
+            let falloff = (1.0 - distance / (self.spec.max_size / 2) as f32).clamp(0.0, 1.0);
+            Some(self.spec.min_damage + (self.spec.max_damage - self.spec.min_damage) * falloff)
+        }
     }
 
-    impl CannonShot {
-        fn count_size(&self) -> i32 {
-            let current_point_in_trajectory = self.distance_to_travel - (self.trajectory.len() as f32);
-            let halfway_point = self.distance_to_travel / 2.0;
-            let over_half = current_point_in_trajectory > halfway_point;
-            let mut result = (100.0 - ((halfway_point - current_point_in_trajectory) / halfway_point) * 100.0) as i32;
+    /// When a cannon shell is allowed to detonate. Other projectiles always go off on contact.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum DetonationRule {
+        /// Shell detonates only when it reaches the end of its trajectory.
+        Airburst,
+        /// Shell detonates only on contact with an enemy tank, otherwise it is a dud.
+        Impact,
+        /// Shell detonates on contact with an enemy tank or at the end of its trajectory, whichever comes first.
+        Both
+    }
 
-            if over_half {
-                result = 100 - (result - 100);
-            }
+    /// What happens when a match's time runs out, or its score limit is reached, with the lead shared.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum TieBreak {
+        /// The match goes on until someone pulls ahead.
+        SuddenDeath,
+        /// The match ends with everyone in the lead sharing it.
+        Draw
+    }
 
-            if result < 20 {
-                return 20
-            }
+    /// What a cannon shell does when it flies into the side of an obstacle.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum ObstacleImpact {
+        Ricochet,
+        Explode
+    }
 
-            result
+    impl DetonationRule {
+        fn detonates_on_impact(&self) -> bool {
+            *self != DetonationRule::Airburst
         }
-        pub fn tick(&mut self) {
-            self.position = self.trajectory.pop_front();
-
-            match self.position {
-                Some(pos) => {
-                    self.last_position = Some(pos.clone());
-                },
-                _ => ()
-            }
+        fn detonates_in_air(&self) -> bool {
+            *self != DetonationRule::Impact
         }
-        pub fn new(from_player_id: i32, from: ControllerPoint, angle: f32, power: i32) -> Self {
-            let distance = MAX_CANNON_SHOT_LENGTH as f32 * (power as f32 / 100.0);
-            let step_size = 30.0;
-            let radians = angle.to_radians();
-            
-            let mut dx = step_size * radians.cos();
-            let mut dy = step_size * radians.sin();
+    }
 
-            let mut trajectory = VecDeque::<ControllerPoint>::new();
-            
-            let mut current_x = from.x;
-            let mut current_y = from.y;
-            for _ in 0..distance.ceil() as i32 {
-                trajectory.push_back(ControllerPoint { x: current_x, y: current_y });
-                current_x += dx;
-                current_y += dy;
-
-                if current_x < 0.0 || current_x > BOUNDS_WIDTH as f32 {
-                    if dx < 0.0 {
-                        dx = 0.0 + dx.abs();
-                    } else if dx > 0.0 {
-                        dx = 0.0 - dx;
-                    }
-                }
+    /// Rules that can differ from one match to the next.
+    #[derive(Debug, Clone, Copy)]
+    pub struct MatchSettings {
+        pub detonation_rule: DetonationRule,
+        pub obstacle_impact: ObstacleImpact,
+        /// Shell speed in px/s when fired at full power.
+        pub muzzle_velocity: f32,
+        /// Fraction of its speed a shell keeps after bouncing off the arena edge.
+        pub wall_restitution: f32,
+        /// Fraction of incoming damage every tank's armor absorbs, 0.0 disables armor.
+        pub armor: f32,
+        /// Whether your own explosions hurt you. They push you around either way.
+        pub self_damage: bool,
+        /// Whether explosions hurt your teammates in team modes. They push them around either way.
+        pub friendly_fire: bool,
+        /// Push in px per tick an explosion gives a tank at its center, falling off to nothing at its edge.
+        pub knockback: f32,
+        /// Push in px per tick a tank gets backwards from a fully loaded shot.
+        pub recoil: f32,
+        pub spawn_strategy: SpawnStrategy,
+        pub game_mode: GameModeType,
+        /// Seconds king of the hill keeps its zone in one place, 0.0 never moves it.
+        pub hill_rotation: f64,
+        /// Seconds a match lasts before whoever leads wins, 0.0 plays until the score limit.
+        pub time_limit: f64,
+        pub tie_break: TieBreak,
+        pub scoring: ScoringRules
+    }
 
-                if current_y < 0.0 || current_y > BOUNDS_HEIGHT as f32 {
-                    if dy < 0.0 {
-                        dy = 0.0 + dy.abs();
-                    } else if dy > 0.0 {
-                        dy = 0.0 - dy;
-                    }
-                }
+    impl Default for MatchSettings {
+        fn default() -> Self {
+            MatchSettings {
+                detonation_rule: DetonationRule::Airburst,
+                obstacle_impact: ObstacleImpact::Ricochet,
+                muzzle_velocity: 1000.0,
+                wall_restitution: 0.8,
+                armor: 0.0,
+                self_damage: false,
+                friendly_fire: false,
+                knockback: 12.0,
+                recoil: 4.0,
+                spawn_strategy: SpawnStrategy::SafeRandom,
+                game_mode: GameModeType::deathmatch,
+                hill_rotation: 30.0,
+                time_limit: 300.0,
+                tie_break: TieBreak::SuddenDeath,
+                scoring: ScoringRules::default()
             }
-            CannonShot {
-                last_position: None,
-                position: None,
-                from_player_id,
-                distance_to_travel: trajectory.len() as f32,
-                trajectory
+        }
+    }
+
+    impl MatchSettings {
+        /// Settings a client sent from the lobby. Anything it left unset keeps its default and
+        /// numbers are kept to what a match can be played with.
+        pub fn from_buffer(settings: &MatchSettingsInfo) -> Self {
+            let defaults = MatchSettings::default();
+            MatchSettings {
+                detonation_rule: match settings.get_detonation_rule() {
+                    DetonationRuleType::airburst => DetonationRule::Airburst,
+                    DetonationRuleType::impact => DetonationRule::Impact,
+                    DetonationRuleType::both => DetonationRule::Both,
+                    DetonationRuleType::empty_9 => defaults.detonation_rule
+                },
+                obstacle_impact: match settings.get_obstacle_impact() {
+                    ObstacleImpactType::ricochet => ObstacleImpact::Ricochet,
+                    ObstacleImpactType::explode => ObstacleImpact::Explode,
+                    ObstacleImpactType::empty_11 => defaults.obstacle_impact
+                },
+                muzzle_velocity: if settings.has_muzzle_velocity() { settings.get_muzzle_velocity().max(MIN_MUZZLE_VELOCITY) } else { defaults.muzzle_velocity },
+                wall_restitution: if settings.has_wall_restitution() { settings.get_wall_restitution().clamp(0.0, 1.0) } else { defaults.wall_restitution },
+                armor: if settings.has_armor() { settings.get_armor().clamp(0.0, MAX_ARMOR) } else { defaults.armor },
+                self_damage: if settings.has_self_damage() { settings.get_self_damage() } else { defaults.self_damage },
+                friendly_fire: if settings.has_friendly_fire() { settings.get_friendly_fire() } else { defaults.friendly_fire },
+                knockback: if settings.has_knockback() { settings.get_knockback().max(0.0) } else { defaults.knockback },
+                recoil: if settings.has_recoil() { settings.get_recoil().max(0.0) } else { defaults.recoil },
+                spawn_strategy: match settings.get_spawn_strategy() {
+                    SpawnStrategyType::random => SpawnStrategy::Random,
+                    SpawnStrategyType::safest => SpawnStrategy::Safest,
+                    SpawnStrategyType::safe_random => SpawnStrategy::SafeRandom,
+                    SpawnStrategyType::empty_12 => defaults.spawn_strategy
+                },
+                game_mode: match settings.get_game_mode() {
+                    GameModeType::empty_18 => defaults.game_mode,
+                    game_mode => game_mode
+                },
+                hill_rotation: if settings.has_hill_rotation() { settings.get_hill_rotation().max(0.0) } else { defaults.hill_rotation },
+                time_limit: if settings.has_time_limit() { settings.get_time_limit().max(0.0) } else { defaults.time_limit },
+                tie_break: match settings.get_tie_break() {
+                    TieBreakType::sudden_death => TieBreak::SuddenDeath,
+                    TieBreakType::draw => TieBreak::Draw,
+                    TieBreakType::empty_20 => defaults.tie_break
+                },
+                scoring: if settings.has_scoring() { ScoringRules::from_buffer(settings.get_scoring()) } else { defaults.scoring }
             }
         }
+        pub fn to_buffer_match_settings(&self) -> MatchSettingsInfo {
+            let mut settings = MatchSettingsInfo::new();
+            settings.set_detonation_rule(match self.detonation_rule {
+                DetonationRule::Airburst => DetonationRuleType::airburst,
+                DetonationRule::Impact => DetonationRuleType::impact,
+                DetonationRule::Both => DetonationRuleType::both
+            });
+            settings.set_obstacle_impact(match self.obstacle_impact {
+                ObstacleImpact::Ricochet => ObstacleImpactType::ricochet,
+                ObstacleImpact::Explode => ObstacleImpactType::explode
+            });
+            settings.set_muzzle_velocity(self.muzzle_velocity);
+            settings.set_wall_restitution(self.wall_restitution);
+            settings.set_armor(self.armor);
+            settings.set_self_damage(self.self_damage);
+            settings.set_friendly_fire(self.friendly_fire);
+            settings.set_knockback(self.knockback);
+            settings.set_recoil(self.recoil);
+            settings.set_spawn_strategy(match self.spawn_strategy {
+                SpawnStrategy::Random => SpawnStrategyType::random,
+                SpawnStrategy::Safest => SpawnStrategyType::safest,
+                SpawnStrategy::SafeRandom => SpawnStrategyType::safe_random
+            });
+            settings.set_game_mode(self.game_mode);
+            settings.set_hill_rotation(self.hill_rotation);
+            settings.set_time_limit(self.time_limit);
+            settings.set_tie_break(match self.tie_break {
+                TieBreak::SuddenDeath => TieBreakType::sudden_death,
+                TieBreak::Draw => TieBreakType::draw
+            });
+            settings.set_scoring(self.scoring.to_buffer_scoring_rules());
+            settings
+        }
     }
 
     #[derive(Debug)]
@@ -223,37 +567,81 @@ pub mod gamelogic {
         tank_rotation: f32,
         target_rotation: f32,
         score: i32,
-        is_loading_cannon: bool,
-        power_loaded: i32,
-        cannon_shot: Option<CannonShot>,
+        kills: i32,
+        deaths: i32,
+        assists: i32,
+        weapons: Vec<Weapon>,
+        equipped_weapon: usize,
+        is_switching_weapon: bool,
+        projectile: Option<Projectile>,
         input: i32,
         motor: f32,
         delta_x: f32,
         delta_y: f32,
         delta_a: f32,
         cooldown: i32,
+        health: i32,
+        armor: f32,
+        last_killer_id: Option<i32>,
+        /// Enemies that hurt the tank, with the ticks left until the damage is too old for an assist.
+        recent_attackers: HashMap<i32, i32>,
+        /// Led the match on its own when the tick's kills started being scored.
+        has_bounty: bool,
+        effects: Vec<ActiveEffect>,
+        ability: Ability,
+        tank_class: TankClass,
+        /// Only set in team modes.
+        team: Option<Team>,
+        /// Team of the flag the tank is carrying in capture the flag.
+        carrying_flag: Option<Team>,
+        /// Driven by the game mode instead of a client.
+        is_bot: bool,
+        stats: TankStats,
+        smoke_drop: Option<ControllerPoint>,
+        /// Inside a smoke cloud, where homing rockets can't find it.
+        is_concealed: bool,
         player_in_game_status: PlayerInGameStatus,
         input_to_angle_hash: HashMap<PlayerInput, f32>,
     }
 
     impl Player {
-        pub fn new(id: i32, max_height: i32, max_width: i32) -> Self {
+        /// `armor` is the match's armor setting, the class's own armor stacks on top of it.
+        pub fn new(id: i32, position: ControllerPoint, armor: f32, ability: AbilityType, tank_class: TankClass, team: Option<Team>) -> Self {
+            let stats = TankStats::for_class(tank_class);
             Player {
                 id,
-                position: ControllerPoint::random_point(max_height - PLAYER_SIZE as i32, max_width - PLAYER_SIZE as i32),
+                position,
                 cannon_angle: 0.0,
                 tank_rotation: 0.0, 
                 target_rotation: 0.0,
                 score: 0,
+                kills: 0,
+                deaths: 0,
+                assists: 0,
                 input: PlayerInput::NoInput as i32,
                 motor: 0.0,
                 delta_x: 0.0,
                 delta_y: 0.0,
                 delta_a: 0.0,
-                is_loading_cannon: false,
-                cannon_shot: None,
-                power_loaded: 0,
+                weapons: Weapon::loadout(stats.reload_factor),
+                equipped_weapon: 0,
+                is_switching_weapon: false,
+                projectile: None,
                 cooldown: 1,
+                health: MAX_HEALTH,
+                armor: 1.0 - (1.0 - armor) * (1.0 - stats.armor),
+                last_killer_id: None,
+                recent_attackers: HashMap::new(),
+                has_bounty: false,
+                effects: Vec::new(),
+                ability: Ability::new(ability),
+                tank_class,
+                team,
+                carrying_flag: None,
+                is_bot: false,
+                stats,
+                smoke_drop: None,
+                is_concealed: false,
                 player_in_game_status: PlayerInGameStatus::respawning,
                 input_to_angle_hash: HashMap::from([(PlayerInput::Down, 90.0), (PlayerInput::Right, 0.0), (PlayerInput::Left, 180.0), (PlayerInput::Up, 270.0)])
             }
@@ -264,9 +652,65 @@ pub mod gamelogic {
         }
         pub fn die(&mut self) {
             self.player_in_game_status = PlayerInGameStatus::dead;
-            self.cooldown = 120;
+            self.health = 0;
+            self.effects.clear();
+            self.ability.cancel();
         }
-        pub fn tick(&mut self) {
+        /// Always false outside team modes, where nobody has a team.
+        pub fn is_on_team(&self, team: Option<Team>) -> bool {
+            self.team.is_some() && self.team == team
+        }
+        /// What the tank is to one spawning on `team`: in the way either way, but only dangerous as an enemy.
+        fn threat_to(&self, team: Option<Team>, danger: f32) -> Threat {
+            if self.is_on_team(team) {
+                Threat::teammate(self.center(), self.stats.size)
+            } else {
+                Threat::tank(self.center(), self.stats.size, danger)
+            }
+        }
+        /// Dead and respawning tanks can't be hit, targeted or pick anything up.
+        pub fn is_alive(&self) -> bool {
+            self.player_in_game_status == PlayerInGameStatus::alive
+        }
+        /// Applies damage reduced by armor and returns true if it destroyed the tank.
+        pub fn take_damage(&mut self, damage: f32) -> bool {
+            self.health -= (damage * (1.0 - self.armor)).round() as i32;
+            if self.health <= 0 {
+                self.die();
+                return true;
+            }
+            false
+        }
+        pub fn has_effect(&self, power_up_type: PowerUpType) -> bool {
+            self.effects.iter().any(|effect| effect.power_up_type == power_up_type)
+        }
+        /// Collecting a power up that is already active starts its timer over.
+        pub fn add_effect(&mut self, power_up_type: PowerUpType, ticks: i32) {
+            self.effects.retain(|effect| effect.power_up_type != power_up_type);
+            self.effects.push(ActiveEffect { power_up_type, remaining: ticks });
+        }
+        /// Uses up the shield if the tank has one, returns true if it did.
+        pub fn absorb_with_shield(&mut self) -> bool {
+            let had_shield = self.has_effect(PowerUpType::shield);
+            self.effects.retain(|effect| effect.power_up_type != PowerUpType::shield);
+            had_shield
+        }
+        /// Explosions can't hurt a tank behind its energy shield.
+        pub fn is_shielded(&self) -> bool {
+            self.ability.is_using(AbilityType::energy_shield)
+        }
+        /// Multiplies acceleration and top speed.
+        fn speed_factor(&self) -> f32 {
+            let boost = if self.has_effect(PowerUpType::speed_boost) { powerups::SPEED_BOOST } else { 1.0 };
+            if self.carrying_flag.is_some() { boost * modes::FLAG_CARRIER_SPEED } else { boost }
+        }
+        fn blast_scale(&self) -> f32 {
+            if self.has_effect(PowerUpType::blast_radius) { powerups::BLAST_RADIUS_SCALE } else { 1.0 }
+        }
+        pub fn tick(&mut self, settings: &MatchSettings, arena: &Arena) {
+            self.effects.iter_mut().for_each(|effect| effect.remaining -= 1);
+            self.effects.retain(|effect| effect.remaining > 0);
+            self.ability.tick();
 
             if self.cooldown > 0 {
                 self.cooldown -= 1;
@@ -275,7 +719,8 @@ pub mod gamelogic {
 
                     if self.player_in_game_status == PlayerInGameStatus::dead {
                         self.player_in_game_status = PlayerInGameStatus::respawning;
-                        self.position = ControllerPoint::random_point(BOUNDS_HEIGHT - PLAYER_SIZE as i32, BOUNDS_WIDTH - PLAYER_SIZE as i32);
+                        self.health = MAX_HEALTH;
+                        self.weapons.iter_mut().for_each(Weapon::refill);
                         self.cooldown += 60;
                         return;
                     } 
@@ -288,34 +733,82 @@ pub mod gamelogic {
                 return;
             }
 
+            // Out of the match, the game mode didn't give it a respawn.
+            if !self.is_alive() {
+                return;
+            }
+
             self.motor_check();
 
             self.apply_input();
 
             self.check_angle();
 
-            self.check_shooting();
+            self.check_weapon_switch();
+
+            self.check_shooting(settings);
+
+            self.check_ability();
 
             if self.has_movement_input() {
                 self.rotate_towards_target();
             }
 
-            self.apply_motor_to_deltas();
+            self.apply_motor_to_deltas(arena.traction_at(self.center()));
 
             if self.is_moving() {
-                self.check_wall_collision();
+                self.check_wall_collision(arena);
             }
 
             self.translate();
+
+            self.check_obstacle_collision(arena);
+        }
+        fn center(&self) -> ControllerPoint {
+            ControllerPoint { x: self.position.x + self.stats.size / 2.0, y: self.position.y + self.stats.size / 2.0 }
         }
         pub fn check_player_collision(&self, other: &Player) -> bool {
-            !(self.position.x + PLAYER_SIZE <= other.position.x || 
-            self.position.y + PLAYER_SIZE <= other.position.y || 
-            other.position.x + PLAYER_SIZE <= self.position.x ||
-            other.position.y + PLAYER_SIZE <= self.position.y) 
+            !(self.position.x + self.stats.size <= other.position.x || 
+            self.position.y + self.stats.size <= other.position.y || 
+            other.position.x + other.stats.size <= self.position.x ||
+            other.position.y + other.stats.size <= self.position.y) 
+        }
+        /// Sweeps a shell of `radius` from `from` to `to` against the tank and returns
+        /// the point of first contact, so fast shells can't tunnel through. Heights are
+        /// part of the sweep, a shell arcing over the tank doesn't hit it.
+        pub fn check_swept_hit(&self, from: ControllerPoint, from_height: f32, to: ControllerPoint, to_height: f32, radius: f32) -> Option<ControllerPoint> {
+            let min = [self.position.x - radius, self.position.y - radius, -radius];
+            let max = [self.position.x + self.stats.size + radius, self.position.y + self.stats.size + radius, TANK_HEIGHT + radius];
+            let start = [from.x, from.y, from_height];
+            let direction = [to.x - from.x, to.y - from.y, to_height - from_height];
+
+            let mut t_enter: f32 = 0.0;
+            let mut t_exit: f32 = 1.0;
+
+            for axis in 0..3 {
+                if direction[axis].abs() < f32::EPSILON {
+                    if start[axis] < min[axis] || start[axis] > max[axis] {
+                        return None;
+                    }
+                    continue;
+                }
+                let t_min = (min[axis] - start[axis]) / direction[axis];
+                let t_max = (max[axis] - start[axis]) / direction[axis];
+                t_enter = t_enter.max(t_min.min(t_max));
+                t_exit = t_exit.min(t_min.max(t_max));
+
+                if t_enter > t_exit {
+                    return None;
+                }
+            }
+
+            Some(ControllerPoint {
+                x: from.x + direction[0] * t_enter,
+                y: from.y + direction[1] * t_enter
+            })
         }
         pub fn should_tick(&self) -> bool {
-            self.cooldown > 0 || self.cannon_shot.is_some() || self.input > 0 || self.delta_x != 0.0 || self.delta_y != 0.0
+            self.cooldown > 0 || self.projectile.is_some() || self.weapons.iter().any(Weapon::is_busy) || self.ability.is_busy() || !self.effects.is_empty() || self.input > 0 || self.delta_x != 0.0 || self.delta_y != 0.0
         }
         pub fn input(&mut self, input:i32) {
             self.input = input
@@ -326,6 +819,19 @@ pub mod gamelogic {
         pub fn increment_score(&mut self) {
             self.score += 1;
         }
+        pub fn add_score(&mut self, points: i32) {
+            self.score += points;
+        }
+        /// Everyone but the killer who hurt the tank recently enough to share in destroying it.
+        fn assist_ids(&self, killer_id: i32) -> Vec<i32> {
+            let mut ids: Vec<i32> = self.recent_attackers.keys().copied().filter(|id| *id != killer_id).collect();
+            ids.sort();
+            ids
+        }
+        fn forget_old_attackers(&mut self) {
+            self.recent_attackers.values_mut().for_each(|ticks_left| *ticks_left -= 1);
+            self.recent_attackers.retain(|_, ticks_left| *ticks_left > 0);
+        }
 
         fn apply_input(&mut self) {
             let mut angles = Vec::<f32>::new();
@@ -355,22 +861,23 @@ pub mod gamelogic {
                 self.tank_rotation = self.target_rotation;
                 return;
             }
-            self.tank_rotation = Player::normalize_angle(self.tank_rotation + (diff * ANGLE_EASING_FACTOR));
+            self.tank_rotation = Player::normalize_angle(self.tank_rotation + (diff * self.stats.turn_easing));
         }
 
-        fn apply_motor_to_deltas(&mut self) {
-            let brakes = 0.9;
+        fn apply_motor_to_deltas(&mut self, traction: Traction) {
+            let brakes = (1.0 - (1.0 - self.stats.brakes) * traction.friction).clamp(0.0, 1.0);
+            let speed_before = (self.delta_x.powi(2) + self.delta_y.powi(2)).sqrt();
 
             let radians = self.tank_rotation.to_radians();
 
-            let apply_x = self.motor * radians.cos();
-            let apply_y = self.motor * radians.sin();
+            let apply_x = self.motor * traction.grip * radians.cos();
+            let apply_y = self.motor * traction.grip * radians.sin();
 
             if apply_x.abs() < 0.1 {
                 if self.delta_x.abs() < 1.0 {
                     self.delta_x = 0.0;
                 } else if self.delta_x.abs() > 1.0 {
-                    self.delta_x = self.delta_x * brakes;
+                    self.delta_x *= brakes;
                 }
             }
 
@@ -378,17 +885,30 @@ pub mod gamelogic {
                 if self.delta_y.abs() < 1.0 {
                     self.delta_y = 0.0;
                 } else if self.delta_y.abs() > 1.0 {
-                    self.delta_y = self.delta_y * brakes;
+                    self.delta_y *= brakes;
                 }
             }
             
-            self.delta_x += apply_x.round();
-            self.delta_y += apply_y.round();
+            self.delta_x += apply_x;
+            self.delta_y += apply_y;
+
+            // The motor can't drive the tank past its top speed, but a push from an explosion,
+            // recoil or a dash is only braked away.
+            if let Some(top_speed) = self.stats.top_speed.filter(|_| !self.ability.is_using(AbilityType::dash)) {
+                let speed = (self.delta_x.powi(2) + self.delta_y.powi(2)).sqrt();
+                let top_speed = (top_speed * self.speed_factor() * traction.top_speed).max(speed_before * brakes);
+                if speed > top_speed {
+                    self.delta_x *= top_speed / speed;
+                    self.delta_y *= top_speed / speed;
+                }
+            }
         }
 
         fn motor_check(&mut self) {
-            if self.has_movement_input() {
-                self.motor = 1.0;
+            if self.ability.is_using(AbilityType::dash) {
+                self.motor = abilities::DASH_MOTOR;
+            } else if self.has_movement_input() {
+                self.motor = self.stats.motor * self.speed_factor();
             } else {
                 self.motor = 0.0;
             }
@@ -415,9 +935,8 @@ pub mod gamelogic {
             if new_angle < 0.0 {
                 new_angle += 360.0;
             }
-            if self.is_loading_cannon && self.power_loaded < 100 {
-                self.power_loaded += 1;
-            }
+            let reload_rate = if self.has_effect(PowerUpType::rapid_reload) { powerups::RAPID_RELOAD_RATE } else { 1 };
+            self.weapons.iter_mut().for_each(|weapon| weapon.tick(reload_rate));
             self.cannon_angle = new_angle;
             self.position.translate(self.delta_x, self.delta_y);
         }
@@ -447,37 +966,92 @@ pub mod gamelogic {
             }
         }
 
-        fn check_shooting(&mut self) {
-            if self.input.contains(PlayerInput::LoadCannon) && !self.is_loading_cannon {
-                self.is_loading_cannon = true;
+        fn check_weapon_switch(&mut self) {
+            let next = self.input.contains(PlayerInput::NextWeapon);
+            let previous = self.input.contains(PlayerInput::PreviousWeapon);
+
+            if !next && !previous {
+                self.is_switching_weapon = false;
+                return;
+            }
+            if self.is_switching_weapon {
+                return;
+            }
+            self.is_switching_weapon = true;
+            self.weapons[self.equipped_weapon].holster();
+
+            let weapon_count = self.weapons.len();
+            if next {
+                self.equipped_weapon = (self.equipped_weapon + 1) % weapon_count;
+            } else {
+                self.equipped_weapon = (self.equipped_weapon + weapon_count - 1) % weapon_count;
+            }
+        }
+
+        fn check_shooting(&mut self, settings: &MatchSettings) {
+            let origin = FiringOrigin {
+                player_id: self.id,
+                muzzle: self.get_cannon_position(),
+                tank_center: self.center(),
+                cannon_angle: self.cannon_angle,
+                blast_scale: self.blast_scale()
+            };
+            let recoil_power = self.weapons[self.equipped_weapon].recoil_power();
+            if let Some(projectile) = self.weapons[self.equipped_weapon].trigger(&mut self.input, origin, settings) {
+                let radians = self.cannon_angle.to_radians();
+                let recoil = settings.recoil * recoil_power;
+                self.push((-recoil * radians.cos(), -recoil * radians.sin()));
+                self.projectile = Some(projectile);
+            }
+        }
+        fn push(&mut self, (push_x, push_y): (f32, f32)) {
+            self.delta_x += push_x;
+            self.delta_y += push_y;
+        }
+
+        fn check_ability(&mut self) {
+            if !self.input.contains(PlayerInput::UseAbility) || !self.ability.activate() {
                 return;
             }
-            if self.input.contains(PlayerInput::Fire) && self.is_loading_cannon {
-                self.cannon_shot = Some(CannonShot::new( self.id, self.get_cannon_position(), self.cannon_angle, self.power_loaded));
-                self.input -= PlayerInput::Fire as i32;
-                self.is_loading_cannon = false;
-                self.power_loaded = 0;
+            if self.ability.ability_type == AbilityType::smoke {
+                self.smoke_drop = Some(self.center());
             }
         }
 
-        fn check_wall_collision(&mut self) {
+        fn check_wall_collision(&mut self, arena: &Arena) {
             let horizontal_check = (self.position.x + self.delta_x) as i32;
             let vertical_check = (self.position.y + self.delta_y) as i32;
 
-            if vertical_check < 0 || vertical_check + (PLAYER_SIZE as i32) > BOUNDS_HEIGHT {
+            if vertical_check < 0 || vertical_check + (self.stats.size as i32) > arena.height {
                 self.reverse_delta_y()
             }
 
-            if horizontal_check < 0 || horizontal_check + (PLAYER_SIZE as i32) > BOUNDS_WIDTH {
+            if horizontal_check < 0 || horizontal_check + (self.stats.size as i32) > arena.width {
                 self.reverse_delta_x()
             }
         }
 
+        /// Pushes the tank back out of any obstacle it drove into and bounces it off the
+        /// side it hit with half its speed, the same as the arena edge does.
+        fn check_obstacle_collision(&mut self, arena: &Arena) {
+            for obstacle in arena.obstacles.iter() {
+                if let Some(((normal_x, normal_y), depth)) = obstacle.check_box_overlap(self.position, self.stats.size) {
+                    self.position.translate(normal_x * depth, normal_y * depth);
+
+                    let speed_into_obstacle = self.delta_x * normal_x + self.delta_y * normal_y;
+                    if speed_into_obstacle < 0.0 {
+                        self.delta_x -= 1.5 * speed_into_obstacle * normal_x;
+                        self.delta_y -= 1.5 * speed_into_obstacle * normal_y;
+                    }
+                }
+            }
+        }
+
         fn get_cannon_position(&self) -> ControllerPoint {
-            let cannon_radians = (self.cannon_angle as f32).to_radians();
-            let (center_x, center_y) = (self.position.x + PLAYER_SIZE / 2.0, self.position.y + PLAYER_SIZE / 2.0);
-            let dx = CANNON_LENGTH as f32 * cannon_radians.cos();
-            let dy = CANNON_LENGTH as f32 * cannon_radians.sin();
+            let cannon_radians = self.cannon_angle.to_radians();
+            let (center_x, center_y) = (self.position.x + self.stats.size / 2.0, self.position.y + self.stats.size / 2.0);
+            let dx = self.stats.cannon_length * cannon_radians.cos();
+            let dy = self.stats.cannon_length * cannon_radians.sin();
             ControllerPoint {
                 x: dx + center_x,
                 y: dy + center_y
@@ -487,7 +1061,12 @@ pub mod gamelogic {
 
     struct Client {
         status: ClientStatus,
-        lobby_status: ClientLobbyStatus
+        lobby_status: ClientLobbyStatus,
+        map_vote: Option<usize>,
+        ability: AbilityType,
+        tank_class: TankClass,
+        /// The team picked in the lobby, the rest are balanced out when a team match starts.
+        team: Option<Team>
     }
 
     impl Client {
@@ -495,6 +1074,10 @@ pub mod gamelogic {
             Client {
                 status: ClientStatus::lobby,
                 lobby_status: ClientLobbyStatus::waiting,
+                map_vote: None,
+                ability: AbilityType::dash,
+                tank_class: TankClass::medium,
+                team: None,
             }
         }
         pub fn set_ready(&mut self) { 
@@ -513,39 +1096,88 @@ pub mod gamelogic {
         }
     }
 
+    /// A tank destroyed this tick, handed to the game mode once the explosions are done.
+    struct Kill {
+        killer_id: i32,
+        victim_id: i32,
+        weapon: WeaponType,
+        position: ControllerPoint
+    }
+
     pub enum GameControllerTickOutput {
         NotEnoughPlayers,
         ScoreChanged,
         WeHaveAWinner
     }
     pub struct GameController {
-        height: i32,
-        width: i32,
+        arena: Arena,
+        maps: Vec<MapDefinition>,
+        map_rotation_index: usize,
+        generator_settings: GeneratorSettings,
+        next_arena_seed: Option<u64>,
+        tick_rate: f64,
         handle_collisions: VecDeque<(i32, i32)>,
         clients: HashMap<i32, Client>,
         players: HashMap<i32, Player>,
-        cannon_shots: HashMap<i32, CannonShot>,
+        projectiles: HashMap<i32, Projectile>,
         explosions: HashMap<i32, Explosion>,
+        power_ups: HashMap<i32, PowerUp>,
+        power_up_timer: i32,
+        smoke_clouds: HashMap<i32, SmokeCloud>,
         internal_id_count: i32,
         status: GameControllerStatus,
         countdown: i32,
         winner_of_last_game: i32,
+        winning_team_of_last_game: Option<Team>,
+        /// Everyone who shared the win when the last match was a draw.
+        winners_of_last_game: Vec<i32>,
+        last_game_drawn: bool,
+        /// Ticks until the match is decided on the standings, `None` without a time limit.
+        time_left: Option<i32>,
+        is_overtime: bool,
+        events: Vec<ControllerEvent>,
+        settings: MatchSettings,
+        next_match_settings: MatchSettings,
+        mode: Box<dyn GameMode>,
+        /// Built from `next_match_settings` ahead of time, so the lobby can ask it how many players it needs.
+        next_mode: Box<dyn GameMode>,
+        wave_schedule: WaveSchedule,
     }
 
     impl  GameController {
-        pub fn new() -> GameController {
+        /// Matches rotate through the given maps followed by a freshly generated arena. Until the
+        /// first match starts the first map, or the built in layout if there are none, is shown.
+        pub fn new(tick_rate: f64, maps: Vec<MapDefinition>) -> GameController {
             GameController {
+                tick_rate,
+                arena: maps.first().map_or_else(|| Arena::default_layout(BOUNDS_WIDTH, BOUNDS_HEIGHT), MapDefinition::to_arena),
+                maps,
+                map_rotation_index: 0,
+                generator_settings: GeneratorSettings::default(),
+                next_arena_seed: None,
                 winner_of_last_game: 0,
+                winning_team_of_last_game: None,
+                winners_of_last_game: Vec::new(),
+                last_game_drawn: false,
+                time_left: None,
+                is_overtime: false,
                 status: GameControllerStatus::stopped,
                 countdown: 0,
                 internal_id_count: 0,
-                height: BOUNDS_HEIGHT,
-                width: BOUNDS_WIDTH,
                 clients: HashMap::new(),
                 players: HashMap::new(),
-                cannon_shots: HashMap::new(),
+                projectiles: HashMap::new(),
                 explosions: HashMap::new(),
+                power_ups: HashMap::new(),
+                power_up_timer: 0,
+                smoke_clouds: HashMap::new(),
                 handle_collisions: VecDeque::<(i32, i32)>::new(),
+                events: Vec::new(),
+                settings: MatchSettings::default(),
+                next_match_settings: MatchSettings::default(),
+                mode: modes::create(&MatchSettings::default(), tick_rate, &WaveSchedule::default()),
+                next_mode: modes::create(&MatchSettings::default(), tick_rate, &WaveSchedule::default()),
+                wave_schedule: WaveSchedule::default(),
             }
         }
 
@@ -554,17 +1186,82 @@ pub mod gamelogic {
         }
 
         pub fn clients_ready(&self) -> bool {
-            self.clients.len() > 1 && self.clients.iter().all(|(_, client)| client.lobby_status == ClientLobbyStatus::ready)
+            let ready = self.clients.values().filter(|client| client.lobby_status == ClientLobbyStatus::ready).count();
+            if self.next_mode.is_solo() {
+                return ready >= self.next_mode.min_players();
+            }
+            self.clients.len() >= self.next_mode.min_players() && ready == self.clients.len()
         }
         pub fn set_client_ready_for_war(&mut self, id:i32) {
             if let Some(client) = self.clients.get_mut(&id) {
                 client.set_ready()
             }
         }
+        /// Settings take effect when the next match starts counting down.
+        pub fn set_match_settings(&mut self, settings: MatchSettings) {
+            self.next_match_settings = settings;
+            self.next_mode = modes::create(&self.next_match_settings, self.tick_rate, &self.wave_schedule);
+        }
+        /// Takes effect the next time a generated arena comes up.
+        pub fn set_generator_settings(&mut self, settings: GeneratorSettings) {
+            self.generator_settings = settings;
+        }
+        /// Takes effect when the next survival match starts counting down.
+        pub fn set_wave_schedule(&mut self, schedule: WaveSchedule) {
+            self.wave_schedule = schedule;
+            self.next_mode = modes::create(&self.next_match_settings, self.tick_rate, &self.wave_schedule);
+        }
+        pub fn vote_for_map(&mut self, client_id: i32, map_id: &str) {
+            let map_index = match map_id {
+                generator::GENERATED_ARENA_ID => Some(self.maps.len()),
+                _ => self.maps.iter().position(|map| map.id() == map_id)
+            };
+            if let (Some(client), Some(map_index)) = (self.clients.get_mut(&client_id), map_index) {
+                client.map_vote = Some(map_index);
+            }
+        }
+        /// Votes for a generated arena built from `seed`, so a layout seen in the lobby can be played again.
+        pub fn replay_arena_seed(&mut self, client_id: i32, seed: u64) {
+            if let Some(client) = self.clients.get_mut(&client_id) {
+                client.map_vote = Some(self.maps.len());
+                self.next_arena_seed = Some(seed);
+            }
+        }
+        pub fn choose_ability(&mut self, client_id: i32, ability: AbilityType) {
+            if let Some(client) = self.clients.get_mut(&client_id) {
+                client.ability = ability;
+            }
+        }
+        pub fn choose_tank_class(&mut self, client_id: i32, tank_class: TankClass) {
+            if let Some(client) = self.clients.get_mut(&client_id) {
+                client.tank_class = tank_class;
+            }
+        }
+        pub fn choose_team(&mut self, client_id: i32, team: Team) {
+            if let Some(client) = self.clients.get_mut(&client_id) {
+                client.team = Some(team);
+            }
+        }
         pub fn start_countdown(&mut self) {
+            self.settings = self.next_match_settings;
+            let next_mode = modes::create(&self.next_match_settings, self.tick_rate, &self.wave_schedule);
+            self.mode = std::mem::replace(&mut self.next_mode, next_mode);
+            self.choose_next_map();
+            self.mode.start(&self.arena);
             self.players.clear();
+            self.power_ups.clear();
+            self.smoke_clouds.clear();
+            self.power_up_timer = (powerups::PICKUP_INTERVAL * self.tick_rate) as i32;
+            self.time_left = (self.settings.time_limit > 0.0 && self.mode.has_clock()).then_some((self.settings.time_limit * self.tick_rate) as i32);
+            self.is_overtime = false;
+            let teams = if self.mode.has_teams() { self.assign_teams() } else { HashMap::new() };
+            let players_team = self.mode.players_team();
             for (id, client) in self.clients.iter_mut().filter(|(_, client)| client.lobby_status == ClientLobbyStatus::ready) {
-                self.players.insert(*id, Player::new(*id, self.height, self.width));
+                let size = TankStats::for_class(client.tank_class).size;
+                let team = teams.get(id).copied().or(players_team);
+                let threats: Vec<Threat> = self.players.values().map(|player| player.threat_to(team, 1.0)).collect();
+                let position = spawns::choose_spawn(self.settings.spawn_strategy, &self.arena, &threats, size, team);
+                self.players.insert(*id, Player::new(*id, position, self.settings.armor, client.ability, client.tank_class, team));
                 client.go_to_war();
             }
             self.status = GameControllerStatus::countdown;
@@ -579,19 +1276,41 @@ pub mod gamelogic {
         pub fn is_counting_down(&self) -> bool {
             self.status == GameControllerStatus::countdown
         }
+        /// Ends the match once the mode has a result or the time is up. A shared lead is played
+        /// on in sudden death instead when the tie break says so.
         pub fn check_for_winner(&mut self) -> Option<GameControllerTickOutput> {
-            if let Some((id, _)) = self.players.iter().find(|(_, player)| player.get_score() >= 5) {
-                self.winner_of_last_game = *id;
-                self.stop();
-                return Some(GameControllerTickOutput::WeHaveAWinner)
+            let result = if self.is_overtime || self.time_left == Some(0) {
+                self.mode.standings(&self.players)
+            } else {
+                self.mode.result(&self.players)?
+            };
+            if matches!(result, MatchResult::Draw(_)) && self.settings.tie_break == TieBreak::SuddenDeath && self.mode.has_clock() {
+                if !self.is_overtime {
+                    self.is_overtime = true;
+                    self.events.push(ControllerEvent::Overtime);
+                }
+                return None;
             }
-            None
+            let (winner_id, winning_team, winners, draw) = match result {
+                MatchResult::Winner(id) => (id, None, vec![id], false),
+                MatchResult::WinningTeam(team) => (0, Some(team), Vec::new(), false),
+                MatchResult::NoWinner => (0, None, Vec::new(), false),
+                MatchResult::Draw(ids) => (0, None, ids, true)
+            };
+            self.winner_of_last_game = winner_id;
+            self.winning_team_of_last_game = winning_team;
+            self.winners_of_last_game = winners;
+            self.last_game_drawn = draw;
+            self.events.push(ControllerEvent::MatchEnd { winner_id, winning_team, draw });
+            self.stop();
+            Some(GameControllerTickOutput::WeHaveAWinner)
         }
         pub fn start(&mut self) {
             if self.status != GameControllerStatus::countdown {
                 return;
             }
             self.status = GameControllerStatus::playing;
+            self.events.push(ControllerEvent::MatchStart);
         }
         pub fn stop(&mut self) {
             self.status = GameControllerStatus::stopped;
@@ -599,80 +1318,174 @@ pub mod gamelogic {
                 player.die()
             }
             self.explosions.clear();
-            self.cannon_shots.clear();
+            self.projectiles.clear();
             self.clients.iter_mut().for_each(|(_, client)| client.back_to_lobby_and_wait());
         }
         pub fn tick(&mut self) -> Option<GameControllerTickOutput> {
-            let mut cannon_shot_ids_marked_for_remove = Vec::with_capacity(self.cannon_shots.len());
+            let mut projectile_ids_marked_for_remove = Vec::with_capacity(self.projectiles.len());
             let mut explosions_marked_for_remove = Vec::with_capacity(self.explosions.len());
-            let mut give_scores_to_these_players = Vec::<i32>::new();
+            let mut kills = Vec::<Kill>::new();
+            let mut destroyed_obstacle_ids = Vec::<i32>::new();
+            let mut barrel_explosions = Vec::<Explosion>::new();
 
-            if self.in_game_clients() < 2 {
+            if self.in_game_clients() < self.mode.min_players() {
+                self.events.push(ControllerEvent::MatchEnd { winner_id: 0, winning_team: None, draw: false });
                 self.stop();
                 return Some(GameControllerTickOutput::NotEnoughPlayers)
             }
+            if !self.is_overtime {
+                self.time_left = self.time_left.map(|ticks| (ticks - 1).max(0));
+            }
 
             while let Some(player_id_pair) = self.handle_collisions.pop_front() {
-                let player_ids = vec![player_id_pair.0, player_id_pair.1];
+                let player_ids = [player_id_pair.0, player_id_pair.1];
                 let mut players: Vec<&mut Player> = self.players
                     .iter_mut()
                     .filter(|(id, _)| player_ids.contains(id))
                     .map(|(_, player)| player)
                     .collect();
 
-                if let [first, second] = &mut players[..] {
-                    GameController::handle_collision(first, second);
-                } else {
-                    panic!("Failed to obtain two mutable references to players");
-                }
+                // One of them may have left the match, or been taken off the arena by the mode.
+                let [first, second] = &mut players[..] else {
+                    continue;
+                };
+                GameController::handle_collision(first, second);
             }
 
-            if !self.cannon_shots.is_empty() {
-                for (id, cannon_shot) in self.cannon_shots.iter_mut() {
-                    cannon_shot.tick();
-                    if let None = cannon_shot.position {
-                        self.internal_id_count += 1;
-                        cannon_shot_ids_marked_for_remove.push(*id);
-                        self.explosions.insert(self.internal_id_count, Explosion::new(cannon_shot.from_player_id, cannon_shot.last_position.unwrap()));
+            self.tick_smoke_clouds();
+            self.players.values_mut().for_each(Player::forget_old_attackers);
+
+            if !self.projectiles.is_empty() {
+                let delta_time = (1.0 / self.tick_rate) as f32;
+                for (id, projectile) in self.projectiles.iter_mut() {
+                    match projectile.tick(delta_time, &self.players, &self.settings, &self.arena) {
+                        ProjectileOutcome::Detonate(position) => {
+                            self.internal_id_count += 1;
+                            projectile_ids_marked_for_remove.push(*id);
+                            let explosion = Explosion::new(projectile.owner_id(), projectile.weapon_type(), position).scaled(projectile.blast_scale()).launched(projectile.launch());
+                            self.explosions.insert(self.internal_id_count, explosion);
+                        },
+                        ProjectileOutcome::Expired => {
+                            projectile_ids_marked_for_remove.push(*id);
+                        },
+                        ProjectileOutcome::InFlight => ()
                     }
                 }
             }
             if !self.explosions.is_empty() {
+                let self_damage = self.settings.self_damage;
+                let friendly_fire = self.settings.friendly_fire;
+                let hit_markers = self.mode.shows_hit_markers();
+                let assist_ticks = (self.settings.scoring.assist_window * self.tick_rate) as i32;
                 for (id, explosion) in self.explosions.iter_mut() {
                     explosion.tick();
                     if explosion.size <= 0 {
                         explosions_marked_for_remove.push(*id);
+                        if let Some(miss) = explosion.hit_marker(0, 0).filter(|_| hit_markers && !explosion.has_hit) {
+                            self.events.push(miss);
+                        }
                         continue;
                     }
-                    for (_, player) in self.players.iter_mut().filter(|(id, player)| explosion.from_player_id != **id && explosion.check_for_hit(player.position) && player.cooldown == 0) {
-                        player.die();
-                        give_scores_to_these_players.push(explosion.from_player_id);
+                    let from_player_id = explosion.from_player_id;
+                    let from_team = self.players.get(&from_player_id).and_then(|player| player.team);
+                    for (id, player) in self.players.iter_mut().filter(|(_, player)| player.is_alive()) {
+                        if !self.arena.has_line_of_sight(explosion.position, player.center(), TANK_HEIGHT / 2.0) {
+                            continue;
+                        }
+                        let Some(damage) = explosion.check_for_damage(*id, player.position, player.stats.size) else {
+                            continue;
+                        };
+                        player.push(explosion.knockback(player.position, player.stats.size, self.settings.knockback));
+                        let is_teammate = from_player_id != *id && player.is_on_team(from_team);
+                        if (!self_damage && from_player_id == *id) || (!friendly_fire && is_teammate) || player.is_shielded() || player.absorb_with_shield() {
+                            continue;
+                        }
+                        let health_before = player.health;
+                        let destroyed = player.take_damage(damage);
+                        explosion.has_hit = true;
+                        if from_player_id != *id && !is_teammate {
+                            player.recent_attackers.insert(from_player_id, assist_ticks);
+                        }
+                        if let Some(hit) = explosion.hit_marker(*id, health_before - player.health).filter(|_| hit_markers) {
+                            self.events.push(hit);
+                        }
+                        if destroyed {
+                            kills.push(Kill { killer_id: from_player_id, victim_id: *id, weapon: explosion.weapon, position: player.position });
+                        }
                     };
+                    for obstacle in self.arena.obstacles.iter_mut() {
+                        let Some(damage) = explosion.check_for_obstacle_damage(obstacle) else {
+                            continue;
+                        };
+                        if !obstacle.take_damage(damage) {
+                            self.events.push(ControllerEvent::ObstacleDamaged { obstacle_id: obstacle.id, player_id: from_player_id, hit_points: obstacle.hit_points.unwrap_or(0) });
+                            continue;
+                        }
+                        destroyed_obstacle_ids.push(obstacle.id);
+                        self.events.push(ControllerEvent::ObstacleDestroyed { obstacle_id: obstacle.id, player_id: from_player_id, position: obstacle.center() });
+                        if obstacle.material == ObstacleMaterial::explosive_barrel {
+                            // Whoever set off the chain gets the credit for what the barrel blows up.
+                            barrel_explosions.push(Explosion::new(from_player_id, WeaponType::barrel, obstacle.center()));
+                        }
+                    }
                 }
             }
+            let score_changed = self.handle_kills(kills);
+            if !destroyed_obstacle_ids.is_empty() {
+                self.arena.obstacles.retain(|obstacle| !destroyed_obstacle_ids.contains(&obstacle.id));
+            }
+            for explosion in barrel_explosions {
+                self.internal_id_count += 1;
+                self.explosions.insert(self.internal_id_count, explosion);
+            }
 
+            let mut respawned_player_ids = Vec::<i32>::new();
             for player in self.players.values_mut().filter(|player| player.should_tick()) {
-                player.tick();
-                if let Some(cannon_shot) = player.cannon_shot.take() {
+                let status_before_tick = player.player_in_game_status;
+                player.tick(&self.settings, &self.arena);
+                if status_before_tick == PlayerInGameStatus::dead && player.player_in_game_status == PlayerInGameStatus::respawning {
+                    respawned_player_ids.push(player.id);
+                }
+                if let Some(position) = player.smoke_drop.take() {
+                    self.internal_id_count += 1;
+                    self.smoke_clouds.insert(self.internal_id_count, SmokeCloud::new(position));
+                }
+                if let Some(projectile) = player.projectile.take() {
                     self.internal_id_count += 1;
-                    self.cannon_shots.insert(self.internal_id_count, cannon_shot);
+                    self.events.push(ControllerEvent::ShotFired { player_id: player.id, weapon: projectile.weapon_type(), position: projectile.position() });
+                    self.projectiles.insert(self.internal_id_count, projectile);
                 }
             }
 
+            let mode_tick = self.mode.tick(&mut self.players, &self.arena, &mut self.events);
+            if mode_tick.new_round {
+                self.start_new_round();
+            }
+            let mode_kills = mode_tick.kills.iter()
+                .filter_map(|victim_id| self.players.get(victim_id).map(|victim| Kill { killer_id: 0, victim_id: *victim_id, weapon: WeaponType::empty_8, position: victim.position }))
+                .collect();
+            let score_changed = self.handle_kills(mode_kills) || score_changed;
+
+            for id in respawned_player_ids {
+                let (size, team) = self.players.get(&id).map_or((PLAYER_SIZE, None), |player| (player.stats.size, player.team));
+                let position = spawns::choose_spawn(self.settings.spawn_strategy, &self.arena, &self.spawn_threats(id), size, team);
+                if let Some(player) = self.players.get_mut(&id) {
+                    player.position = position;
+                    self.events.push(ControllerEvent::Respawn { player_id: id, position });
+                }
+            }
+
+            self.tick_power_ups();
+
             self.check_player_collisions();
 
-            for id in cannon_shot_ids_marked_for_remove {
-                self.cannon_shots.remove_entry(&id);
+            for id in projectile_ids_marked_for_remove {
+                self.projectiles.remove_entry(&id);
             }
             for id in explosions_marked_for_remove {
                 self.explosions.remove_entry(&id);
             }
-            if !give_scores_to_these_players.is_empty() {
-                for id in give_scores_to_these_players.as_mut_slice() {
-                    if let Some(player) = self.players.get_mut(&id) {
-                        player.increment_score();
-                    }
-                }
+            if score_changed || mode_tick.score_changed {
                 return Some(GameControllerTickOutput::ScoreChanged);
             }
             self.check_for_winner()
@@ -688,23 +1501,46 @@ pub mod gamelogic {
         pub fn add_client(&mut self, id: i32) {
             self.clients.insert(id, Client::new());
         }
+        /// Takes the client out of the match it is playing and back to the lobby, or takes back a
+        /// ready in the lobby. A match left without enough players ends on its next tick.
+        pub fn leave_match(&mut self, client_id: i32) {
+            if let Some(client) = self.clients.get_mut(&client_id) {
+                client.back_to_lobby_and_wait();
+                self.remove_player(client_id);
+            }
+        }
         pub fn drop_client(&mut self, client_id: i32) {
-            if let Some((id, _)) = self.clients.remove_entry(&client_id) {
-                self.players.remove_entry(&id);
-            };
+            if self.clients.remove(&client_id).is_some() {
+                self.remove_player(client_id);
+            }
+        }
+        fn remove_player(&mut self, id: i32) {
+            self.players.remove(&id);
+            self.handle_collisions.retain(|(first, second)| *first != id && *second != id);
         }
         pub fn lobby_output(&mut self) -> ServerLobbyResponse {
             let mut lobby_response = ServerLobbyResponse::new();
             let is_playing = self.is_playing();
+            let is_stopped = self.status == GameControllerStatus::stopped;
             let clients_in_lobby = self.clients.iter_mut().map(|(id, client)| {
                 let mut client_info = ClientInfo::new();
                 client_info.set_id(*id);
                 client_info.set_status(client.status);
                 client_info.set_lobby_status(client.lobby_status);
+                client_info.set_ability(client.ability);
+                client_info.set_tank_class(client.tank_class);
+                let team = match self.players.get(id) {
+                    Some(player) if !is_stopped => player.team,
+                    _ => client.team
+                };
+                client_info.set_team(team.unwrap_or(Team::empty_19));
                 client_info.set_score(0);
                 if is_playing {
                     if let Some(player) = self.players.get(id) {
-                        client_info.set_score(player.get_score())
+                        client_info.set_score(player.get_score());
+                        client_info.set_kills(player.kills);
+                        client_info.set_deaths(player.deaths);
+                        client_info.set_assists(player.assists);
                     }
                 }
                 client_info
@@ -715,8 +1551,64 @@ pub mod gamelogic {
             lobby_response.set_countdown_amount(self.countdown);
             lobby_response.set_field_type(MessageType::lobby_message);
             lobby_response.set_winner_of_last_game(self.winner_of_last_game);
+            lobby_response.set_winning_team_of_last_game(self.winning_team_of_last_game.unwrap_or(Team::empty_19));
+            lobby_response.set_winners_of_last_game(self.winners_of_last_game.clone());
+            lobby_response.set_last_game_drawn(self.last_game_drawn);
+            lobby_response.set_team_scores(RepeatedField::from_vec(self.mode.team_scores().into_iter().map(|(team, score)| {
+                let mut team_score = TeamScore::new();
+                team_score.set_team(team);
+                team_score.set_score(score);
+                team_score
+            }).collect()));
+            lobby_response.set_map_id(self.arena.id.clone());
+            lobby_response.set_map_name(self.arena.name.clone());
+            lobby_response.set_arena_seed(self.arena.seed.unwrap_or(0));
+            lobby_response.set_game_mode(match self.status {
+                GameControllerStatus::stopped => self.next_match_settings.game_mode,
+                _ => self.mode.mode_type()
+            });
+            let map_names = self.maps.iter().map(|map| (map.id(), map.name())).chain([(generator::GENERATED_ARENA_ID, "Generated")]);
+            self.mode.write_lobby(&mut lobby_response);
+            lobby_response.set_maps(RepeatedField::from_vec(self.map_votes().into_iter().zip(map_names).map(|(votes, (id, name))| {
+                let mut map_info = MapInfo::new();
+                map_info.set_id(id.to_string());
+                map_info.set_name(name.to_string());
+                map_info.set_votes(votes);
+                map_info
+            }).collect()));
+            lobby_response.set_match_settings(self.next_match_settings.to_buffer_match_settings());
+            lobby_response.set_generator_settings(self.generator_settings.to_buffer_generator_settings());
             lobby_response
         }
+        pub fn arena_output(&self) -> ServerArenaResponse {
+            let mut arena_response = ServerArenaResponse::new();
+            arena_response.set_width(self.arena.width);
+            arena_response.set_height(self.arena.height);
+            arena_response.set_obstacles(RepeatedField::from_vec(self.arena.obstacles.iter().map(|obstacle| obstacle.to_buffer_obstacle()).collect()));
+            arena_response.set_spawn_points(RepeatedField::from_vec(self.arena.spawn_points.iter().map(|spawn_point| spawn_point.to_buffer_spawn_point()).collect()));
+            arena_response.set_map_id(self.arena.id.clone());
+            arena_response.set_map_name(self.arena.name.clone());
+            arena_response.set_seed(self.arena.seed.unwrap_or(0));
+            if self.mode.has_teams() {
+                arena_response.set_bases(RepeatedField::from_vec([Team::red, Team::blue].into_iter().map(|team| {
+                    Base { team, position: self.arena.base_of(team) }.to_buffer_base()
+                }).collect()));
+            }
+            arena_response.set_terrain(RepeatedField::from_vec(self.arena.terrain.iter().map(|zone| zone.to_buffer_terrain_zone()).collect()));
+            arena_response.set_field_type(MessageType::arena);
+            arena_response
+        }
+        pub fn events_output(&mut self) -> Option<ServerGameEventsResponse> {
+            if self.events.is_empty() {
+                return None;
+            }
+            let events = self.events.drain(..).map(|event| event.to_buffer_event()).collect();
+
+            let mut events_response = ServerGameEventsResponse::new();
+            events_response.set_events(RepeatedField::from_vec(events));
+            events_response.set_field_type(MessageType::game_events);
+            Some(events_response)
+        }
         pub fn in_game_output(&mut self) -> ServerGameFrameResponse {
             let mut player_response_vec: Vec<PlayerInGameResponse> = Vec::<PlayerInGameResponse>::new();
             let mut cannon_shot_response_vec = Vec::<CannonEventResponse>::new();
@@ -728,6 +1620,18 @@ pub mod gamelogic {
                 player_response.set_cannon_position(player.get_cannon_position().to_buffer_point());
                 player_response.set_id(player.id);
                 player_response.set_in_game_status(player.player_in_game_status);
+                player_response.set_health(player.health);
+                player_response.set_max_health(MAX_HEALTH);
+                player_response.set_weapon(player.weapons[player.equipped_weapon].weapon_type);
+                player_response.set_ammo(player.weapons[player.equipped_weapon].ammo_for_output());
+                player_response.set_tank_class(player.tank_class);
+                player_response.set_team(player.team.unwrap_or(Team::empty_19));
+                player_response.set_bot(player.is_bot);
+                player_response.set_size(player.stats.size as i32);
+                player_response.set_ability(player.ability.ability_type);
+                player_response.set_ability_active(player.ability.is_active());
+                player_response.set_ability_cooldown(player.ability.cooldown);
+                player_response.set_effects(RepeatedField::from_vec(player.effects.iter().map(|effect| effect.to_buffer_effect()).collect()));
                 player_response.set_tank_rotation(player.tank_rotation as i32); // frontend graphic thinks 0 but the framework thinks 0 as left
                 player_response_vec.push(player_response);
             }
@@ -738,20 +1642,21 @@ pub mod gamelogic {
                 explosion_response.set_position(explosion.position.to_buffer_point());
                 explosion_response.set_size(explosion.size);
                 explosion_response.set_from_id(explosion.from_player_id);
+                explosion_response.set_weapon(explosion.weapon);
                 explosion_response.set_id(*id);
                 exlosion_response_vec.push(explosion_response);
             }
 
-            for (id, cannon_shot) in self.cannon_shots.iter() {
-                if let Some(position) = cannon_shot.position {
-                    let mut cannon_shot_response = CannonEventResponse::new();
-                    cannon_shot_response.set_position(position.to_buffer_point());
-                    cannon_shot_response.set_size(cannon_shot.count_size());
-                    cannon_shot_response.set_from_id(cannon_shot.from_player_id);
-                    cannon_shot_response.set_id(*id);
+            for (id, projectile) in self.projectiles.iter() {
+                let mut cannon_shot_response = CannonEventResponse::new();
+                cannon_shot_response.set_position(projectile.position().to_buffer_point());
+                cannon_shot_response.set_size(projectile.count_size());
+                cannon_shot_response.set_height(projectile.height() as i32);
+                cannon_shot_response.set_from_id(projectile.owner_id());
+                cannon_shot_response.set_id(*id);
+                cannon_shot_response.set_weapon(projectile.weapon_type());
 
-                    cannon_shot_response_vec.push(cannon_shot_response);
-                } 
+                cannon_shot_response_vec.push(cannon_shot_response);
             }
 
             let mut server_output = ServerGameFrameResponse::new();
@@ -761,21 +1666,180 @@ pub mod gamelogic {
             server_output.set_players(players);
             server_output.set_shots(cannon_shots);
             server_output.set_explosions(explosions);
+            server_output.set_smoke_clouds(RepeatedField::from_vec(self.smoke_clouds.iter().map(|(id, smoke_cloud)| smoke_cloud.to_buffer_smoke_cloud(*id)).collect()));
+            server_output.set_power_ups(RepeatedField::from_vec(self.power_ups.iter().map(|(id, power_up)| power_up.to_buffer_power_up(*id)).collect()));
+            // -1 without a time limit, like unlimited ammo.
+            server_output.set_time_left(self.time_left.unwrap_or(-1));
+            server_output.set_overtime(self.is_overtime);
+            self.mode.write_frame(&mut server_output);
             server_output.set_field_type(crate::MessageType::frame);
 
             server_output
         }
-        fn check_player_collisions(&mut self) {
-            for player in self.players.values() {
-                for other_player in self.players.values().filter(|other_player| player.id != other_player.id) {
-                    if player.check_player_collision(other_player) {
-                        if !self.handle_collisions.iter().any(|(first, second)| [*first, *second].contains(&player.id) || [*first, *second].contains(&other_player.id)) {
-                            self.handle_collisions.push_back((player.id, other_player.id));
+        /// Tallies the kills, deaths and assists, lets the game mode score them and decide when,
+        /// if ever, the victims respawn. Returns true if any score or tally changed.
+        fn handle_kills(&mut self, kills: Vec<Kill>) -> bool {
+            let mut score_changed = !kills.is_empty();
+            // Taken before any of the kills is scored, so the order they are handled in can't move the bounty.
+            let leader_id = scoring::sole_leader(&self.players);
+            for player in self.players.values_mut() {
+                player.has_bounty = Some(player.id) == leader_id;
+            }
+            for kill in kills {
+                self.tally_kill(&kill);
+                score_changed |= self.mode.on_kill(kill.killer_id, kill.victim_id, &mut self.players);
+                if let Some(victim) = self.players.get_mut(&kill.victim_id) {
+                    victim.recent_attackers.clear();
+                    victim.last_killer_id = Some(kill.killer_id).filter(|killer_id| *killer_id != kill.victim_id);
+                    match self.mode.respawn_delay(victim) {
+                        Some(delay) => victim.cooldown = delay,
+                        None => {
+                            victim.cooldown = 0;
+                            victim.player_in_game_status = PlayerInGameStatus::spectating;
                         }
                     }
                 }
+                self.events.push(ControllerEvent::Kill { killer_id: kill.killer_id, victim_id: kill.victim_id, weapon: kill.weapon, position: kill.position });
+            }
+            score_changed
+        }
+        /// Destroying your own tank or a teammate's is a death but no kill.
+        fn tally_kill(&mut self, kill: &Kill) {
+            let Some(victim) = self.players.get_mut(&kill.victim_id) else {
+                return;
+            };
+            victim.deaths += 1;
+            let victim_team = victim.team;
+            let assist_ids = victim.assist_ids(kill.killer_id);
+            if let Some(killer) = self.players.get_mut(&kill.killer_id).filter(|killer| killer.id != kill.victim_id && !killer.is_on_team(victim_team)) {
+                killer.kills += 1;
+            }
+            for id in assist_ids {
+                if let Some(assistant) = self.players.get_mut(&id) {
+                    assistant.assists += 1;
+                }
+            }
+        }
+        /// Wrecks every tank, survivors and spectators alike, so they all respawn next tick on
+        /// an arena cleared of whatever the last round left flying or lying around.
+        fn start_new_round(&mut self) {
+            for player in self.players.values_mut() {
+                player.die();
+                player.cooldown = 1;
+            }
+            self.projectiles.clear();
+            self.explosions.clear();
+            self.smoke_clouds.clear();
+        }
+        /// Burns down the smoke clouds and marks the tanks hiding in them.
+        fn tick_smoke_clouds(&mut self) {
+            self.smoke_clouds.values_mut().for_each(|smoke_cloud| smoke_cloud.remaining -= 1);
+            self.smoke_clouds.retain(|_, smoke_cloud| smoke_cloud.remaining > 0);
+            for player in self.players.values_mut() {
+                player.is_concealed = self.smoke_clouds.values().any(|smoke_cloud| smoke_cloud.covers(player.center()));
+            }
+        }
+        /// Hands power ups to the tanks touching them and drops a new one every
+        /// `PICKUP_INTERVAL` seconds while there is room.
+        fn tick_power_ups(&mut self) {
+            let mut collected_ids = Vec::<i32>::new();
+            for (id, power_up) in self.power_ups.iter() {
+                let collector = self.players.values_mut()
+                    .find(|player| player.player_in_game_status == PlayerInGameStatus::alive && power_up.is_touching(player.position, player.stats.size));
+                if let Some(player) = collector {
+                    player.add_effect(power_up.power_up_type, (power_up.duration() * self.tick_rate) as i32);
+                    self.events.push(ControllerEvent::PowerUpCollected { player_id: player.id, power_up: power_up.power_up_type, position: power_up.position });
+                    collected_ids.push(*id);
+                }
+            }
+            for id in collected_ids {
+                self.power_ups.remove(&id);
+            }
+
+            if self.power_up_timer > 0 {
+                self.power_up_timer -= 1;
+                return;
+            }
+            if self.power_ups.len() < powerups::MAX_PICKUPS {
+                let taken: Vec<ControllerPoint> = self.power_ups.values().map(|power_up| power_up.position).collect();
+                self.internal_id_count += 1;
+                self.power_ups.insert(self.internal_id_count, PowerUp::spawn(&self.arena, &taken));
+            }
+            self.power_up_timer = (powerups::PICKUP_INTERVAL * self.tick_rate) as i32;
+        }
+        fn check_player_collisions(&mut self) {
+            let in_arena = |player: &&Player| player.player_in_game_status != PlayerInGameStatus::spectating;
+            for player in self.players.values().filter(in_arena) {
+                for other_player in self.players.values().filter(in_arena).filter(|other_player| player.id != other_player.id) {
+                    if player.check_player_collision(other_player) && !self.handle_collisions.iter().any(|(first, second)| [*first, *second].contains(&player.id) || [*first, *second].contains(&other_player.id)) {
+                        self.handle_collisions.push_back((player.id, other_player.id));
+                    }
+                }
             }
         }
+        /// Everything the tank respawning as `player_id` should keep away from: the other tanks,
+        /// with its killer counting double, explosions and where projectiles in flight are now.
+        fn spawn_threats(&self, player_id: i32) -> Vec<Threat> {
+            let (killer_id, team) = self.players.get(&player_id).map_or((None, None), |player| (player.last_killer_id, player.team));
+            let tanks = self.players.values()
+                .filter(|player| player.id != player_id && !matches!(player.player_in_game_status, PlayerInGameStatus::dead | PlayerInGameStatus::spectating))
+                .map(|player| player.threat_to(team, if Some(player.id) == killer_id { 2.0 } else { 1.0 }));
+            let explosions = self.explosions.values()
+                .map(|explosion| Threat::blast(explosion.position, (explosion.spec.max_size / 2) as f32));
+            let projectiles = self.projectiles.values()
+                .map(|projectile| Threat::blast(projectile.position(), (ExplosionSpec::for_weapon(projectile.weapon_type()).max_size / 2) as f32));
+
+            tanks.chain(explosions).chain(projectiles).collect()
+        }
+        /// Teams for the ready clients. Picks are kept and everyone who didn't pick joins
+        /// whichever team is smaller at the time.
+        fn assign_teams(&self) -> HashMap<i32, Team> {
+            let mut ready_ids: Vec<i32> = self.clients.iter()
+                .filter(|(_, client)| client.lobby_status == ClientLobbyStatus::ready)
+                .map(|(id, _)| *id)
+                .collect();
+            ready_ids.sort();
+            let mut teams: HashMap<i32, Team> = ready_ids.iter()
+                .filter_map(|id| self.clients[id].team.map(|team| (*id, team)))
+                .collect();
+            for id in ready_ids {
+                if teams.contains_key(&id) {
+                    continue;
+                }
+                let reds = teams.values().filter(|team| **team == Team::red).count();
+                let blues = teams.len() - reds;
+                teams.insert(id, if reds <= blues { Team::red } else { Team::blue });
+            }
+            teams
+        }
+        fn map_votes(&self) -> Vec<i32> {
+            let mut votes = vec![0; self.maps.len() + 1];
+            for map_index in self.clients.values().filter_map(|client| client.map_vote) {
+                votes[map_index] += 1;
+            }
+            votes
+        }
+        /// Picks the map with the most votes. Ties, and matches nobody voted for, go to whichever
+        /// comes first in the rotation. The generated arena sits after the loaded maps.
+        fn choose_next_map(&mut self) {
+            let rotation_length = self.maps.len() + 1;
+            let votes = self.map_votes();
+            let most_votes = votes.iter().copied().max().unwrap_or(0);
+            let chosen = (0..rotation_length)
+                .map(|offset| (self.map_rotation_index + offset) % rotation_length)
+                .find(|index| votes[*index] == most_votes)
+                .unwrap_or(self.map_rotation_index);
+
+            self.arena = match self.maps.get(chosen) {
+                Some(map) => map.to_arena(),
+                None => {
+                    let seed = self.next_arena_seed.take().unwrap_or_else(|| thread_rng().gen());
+                    generator::generate(&self.generator_settings, seed)
+                }
+            };
+            self.map_rotation_index = (chosen + 1) % rotation_length;
+            self.clients.values_mut().for_each(|client| client.map_vote = None);
+        }
         fn in_game_clients(&self) -> usize {
             self.clients.values().filter(|client| client.status == ClientStatus::in_game).count()
         }
@@ -2,6 +2,7 @@ use std::collections::hash_map::ValuesMut;
 use std::collections::HashMap;
 use tokio::sync::mpsc::Sender;
 use std::env;
+use std::path::Path;
 use std::time::Duration;
 use protobuf::Message;
 use futures_util::stream::{SplitSink, StreamExt};
@@ -10,8 +11,8 @@ use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::mpsc::Receiver;
 use tokio::time::Instant;
 use tokio_tungstenite::{accept_async, WebSocketStream};
-use backend::gamelogic::{GameController, GameControllerTickOutput};
-use backend::{ClientLobbyStatus, ClientRequestType, InputRequest, MessageType};
+use backend::gamelogic::{GameController, GameControllerTickOutput, GeneratorSettings, MapDefinition, MatchSettings, WaveSchedule};
+use backend::{AbilityType, ClientLobbyStatus, ClientRequestType, InputRequest, MessageType, TankClass, Team};
 use backend::PlayerId;
 
 type TokioMessage = tokio_tungstenite::tungstenite::Message;
@@ -34,9 +35,23 @@ async fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
         println!("Please specify a framerate! Ex. ./backend 60, cargo run -- 60");
+        println!("Maps are loaded from ./maps unless another directory is given. Ex. cargo run -- 60 ./my-maps");
+        println!("Survival waves are loaded from ./waves.json unless another file is given. Ex. cargo run -- 60 ./maps ./my-waves.json");
         return;
     }
     let frame_rate: f64 = args[1].parse().unwrap();
+    let maps_directory = args.get(2).map_or("maps", String::as_str);
+    let (maps, skipped_maps) = MapDefinition::load_dir(Path::new(maps_directory)).unwrap_or_else(|error| {
+        eprintln!("Failed to read maps from {}: {}", maps_directory, error);
+        (Vec::new(), Vec::new())
+    });
+    skipped_maps.iter().for_each(|skipped| eprintln!("{}", skipped));
+    println!("Loaded {} maps", maps.len()); // TODO logging
+    let waves_file = args.get(3).map_or("waves.json", String::as_str);
+    let waves = WaveSchedule::load(Path::new(waves_file)).unwrap_or_else(|error| {
+        println!("Failed to read waves from {}, using the built in ones: {}", waves_file, error); // TODO logging
+        WaveSchedule::default()
+    });
 
     let (sender, receiver) = tokio::sync::mpsc::channel::<TxMessage>(100);
     let mut id_count:i32 = 0;
@@ -48,7 +63,7 @@ async fn main() {
 
     let game_ticker_send = sender.clone();
 
-    tokio::spawn(main_game_loop(receiver));
+    tokio::spawn(main_game_loop(receiver, frame_rate, maps, waves));
     tokio::spawn(async move {
         let tick_rate = Duration::from_secs_f64(1.0 / frame_rate);
         let mut last_tick = Instant::now();
@@ -70,19 +85,44 @@ async fn main() {
     }
 }
 
-async fn main_game_loop(mut receiver: Receiver<TxMessage>) {
-    let mut game_controller: GameController = GameController::new();
+async fn main_game_loop(mut receiver: Receiver<TxMessage>, frame_rate: f64, maps: Vec<MapDefinition>, waves: WaveSchedule) {
+    let mut game_controller: GameController = GameController::new(frame_rate, maps);
+    game_controller.set_wave_schedule(waves);
     let mut connection_pool: HashMap<i32, SplitSink<WebSocketStream<TcpStream>, TokioMessage>> = HashMap::<i32, SplitSink<WebSocketStream<TcpStream>, TokioMessage>>::new();
 
     while let Some(msg) = receiver.recv().await {
         match msg {
             TxMessage::PlayerInLobbyInput(input) => {
-                if input.get_status() == ClientLobbyStatus::ready {
-                    game_controller.set_client_ready_for_war(input.player_id)
+                if !input.get_map_vote().is_empty() {
+                    game_controller.vote_for_map(input.player_id, input.get_map_vote());
+                }
+                if input.get_ability() != AbilityType::empty_15 {
+                    game_controller.choose_ability(input.player_id, input.get_ability());
+                }
+                if input.get_tank_class() != TankClass::empty_16 {
+                    game_controller.choose_tank_class(input.player_id, input.get_tank_class());
+                }
+                if input.get_team() != Team::empty_19 {
+                    game_controller.choose_team(input.player_id, input.get_team());
+                }
+                if input.get_arena_seed() != 0 {
+                    game_controller.replay_arena_seed(input.player_id, input.get_arena_seed());
+                }
+                if input.has_match_settings() {
+                    game_controller.set_match_settings(MatchSettings::from_buffer(input.get_match_settings()));
+                }
+                if input.has_generator_settings() {
+                    game_controller.set_generator_settings(GeneratorSettings::from_buffer(input.get_generator_settings()));
+                }
+                match input.get_status() {
+                    ClientLobbyStatus::ready => game_controller.set_client_ready_for_war(input.player_id),
+                    ClientLobbyStatus::waiting => game_controller.leave_match(input.player_id),
+                    ClientLobbyStatus::empty_4 => ()
                 }
                 send_output_to_all_clients(connection_pool.values_mut(), game_controller.lobby_output()).await;
                 if game_controller.clients_ready() {
                     game_controller.start_countdown();
+                    send_output_to_all_clients(connection_pool.values_mut(), game_controller.arena_output()).await;
                     send_output_to_all_clients(connection_pool.values_mut(), game_controller.in_game_output()).await;
                 }
             },
@@ -95,6 +135,9 @@ async fn main_game_loop(mut receiver: Receiver<TxMessage>) {
 
                 let bytes = new_player_message.write_to_bytes().unwrap();
                 let _ = new_connection.sink.send(TokioMessage::binary(bytes)).await;
+                if game_controller.is_counting_down() || game_controller.is_playing() {
+                    let _ = new_connection.sink.send(TokioMessage::binary(game_controller.arena_output().write_to_bytes().unwrap())).await;
+                }
                 if game_controller.is_counting_down() {
                     let _ = new_connection.sink.send(TokioMessage::binary(game_controller.in_game_output().write_to_bytes().unwrap())).await;
                 }  
@@ -111,6 +154,7 @@ async fn main_game_loop(mut receiver: Receiver<TxMessage>) {
                 connection_pool.remove_entry(&player_id);
                 if !game_controller.is_playing() && !game_controller.is_counting_down() && game_controller.clients_ready()  {
                     game_controller.start_countdown();
+                    send_output_to_all_clients(connection_pool.values_mut(), game_controller.arena_output()).await;
                 }
                 send_output_to_all_clients(connection_pool.values_mut(), game_controller.in_game_output()).await;
                 send_output_to_all_clients(connection_pool.values_mut(), game_controller.lobby_output()).await;
@@ -138,6 +182,9 @@ async fn main_game_loop(mut receiver: Receiver<TxMessage>) {
                     game_controller.countdown();
                     send_output_to_all_clients(connection_pool.values_mut(), game_controller.lobby_output()).await;
                 }
+                if let Some(events) = game_controller.events_output() {
+                    send_output_to_all_clients(connection_pool.values_mut(), events).await;
+                }
             }   
         }
     }